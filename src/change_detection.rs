@@ -0,0 +1,94 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::persistence::write_atomically;
+
+/// Hash of a post body as it was last sent to WriteFreely.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredHash {
+    /// `sha256:<hex>` of the Markdown body.
+    pub content_hash: String,
+    pub last_synced: DateTime<Utc>,
+}
+
+/// JSON file mapping slugs to the hash of the body last synced, so
+/// changed posts can be found without fetching them from WriteFreely.
+pub struct HashStore {
+    path: PathBuf,
+}
+
+impl HashStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> HashStore {
+        HashStore {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// All stored hashes. A missing file is the same as an empty one.
+    pub fn load(&self) -> Result<BTreeMap<String, StoredHash>> {
+        match fs::read_to_string(&self.path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Store the hash of the body just synced for the slug.
+    pub fn record(&self, slug: &str, markdown: &str) -> Result<()> {
+        let mut hashes = self.load()?;
+        hashes.insert(
+            slug.to_owned(),
+            StoredHash {
+                content_hash: content_hash(markdown),
+                last_synced: Utc::now(),
+            },
+        );
+
+        write_atomically(&self.path, &serde_json::to_string_pretty(&hashes)?)
+    }
+}
+
+pub fn content_hash(markdown: &str) -> String {
+    let hex: String = Sha256::digest(markdown.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("sha256:{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_load() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("gemfreely-hashes-{}.json", std::process::id()));
+        let store = HashStore::new(&path);
+
+        store.record("post1", "old")?;
+        store.record("post1", "new")?;
+        store.record("post2", "other")?;
+
+        let hashes = store.load()?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes["post1"].content_hash, content_hash("new"));
+        Ok(())
+    }
+
+    #[test]
+    fn content_hash_is_prefixed_sha256() {
+        assert_eq!(
+            content_hash(""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
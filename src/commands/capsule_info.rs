@@ -0,0 +1,59 @@
+use crate::gemfeed::{Gemfeed, GemfeedParserSettings};
+use crate::output::Reporter;
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use std::result::Result as StdResult;
+use url::Url;
+
+/// Number of entries shown in the capsule info output.
+const SHOWN_ENTRIES: usize = 5;
+
+pub(crate) struct CapsuleInfoCommand<'a> {
+    gemlog_url: &'a str,
+    parser_settings: GemfeedParserSettings<'a>,
+}
+
+impl<'a> TryFrom<&'a Cli> for CapsuleInfoCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::CapsuleInfo { ref gemlog_url }) = cli.command {
+            Ok(Self {
+                gemlog_url,
+                parser_settings: GemfeedParserSettings::try_from(cli)?,
+            })
+        } else {
+            Err(anyhow!("Not a valid capsule-info command"))
+        }
+    }
+}
+
+impl CapsuleInfoCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let url = Url::parse(self.gemlog_url)?;
+        let info = Gemfeed::capsule_info(&url, &self.parser_settings)?;
+
+        let mut lines = vec![
+            format!("Capsule: {}", url),
+            format!("  Meta: {}", info.meta),
+            format!("  MIME type: {}", info.mime),
+            format!("  Response size: {} bytes", info.size),
+            format!("  Detected feed type: {}", info.feed_type),
+        ];
+
+        match info.gemfeed {
+            Ok(gemfeed) => {
+                lines.push(format!("  Entries: {}", gemfeed.entries().count()));
+                for entry in gemfeed.entries().take(SHOWN_ENTRIES) {
+                    let date = entry
+                        .published()
+                        .map(|date| date.format("%Y-%m-%d").to_string())
+                        .unwrap_or_else(|| "no date".to_string());
+                    lines.push(format!("    {} {}", date, entry.title()));
+                }
+            }
+            Err(err) => lines.push(format!("  Could not parse feed: {}", err)),
+        }
+
+        reporter.message(&lines.join("\n"))
+    }
+}
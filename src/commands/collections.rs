@@ -0,0 +1,249 @@
+use crate::commands::list::render_table;
+use crate::output::Reporter;
+use crate::wf::{CollectionInfo, WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use clap::{Subcommand, ValueEnum};
+use std::result::Result as StdResult;
+use url::Url;
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum CollectionsAction {
+    /// Lists the collections with their post counts.
+    List {
+        /// How to print the collections.
+        #[arg(long, value_enum, default_value_t = CollectionsFormat::Table)]
+        format: CollectionsFormat,
+    },
+
+    /// Creates a new collection.
+    Create {
+        /// Alias of the new collection, used in its URL.
+        #[arg(long)]
+        alias: String,
+
+        /// Title of the new collection.
+        #[arg(long)]
+        title: String,
+
+        /// Description of the new collection.
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Do nothing if a collection with the alias already exists.
+        #[arg(long)]
+        if_not_exists: bool,
+    },
+}
+
+/// Output format of the collections list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum CollectionsFormat {
+    Table,
+    Json,
+}
+
+pub(crate) struct CollectionsCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    action: &'a CollectionsAction,
+}
+
+impl<'a> TryFrom<&'a Cli> for CollectionsCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::Collections {
+            ref wf_url,
+            ref action,
+        }) = cli.command
+        {
+            Ok(Self {
+                wf_url,
+                // Collections belong to the user, so no alias is needed.
+                wf_alias: cli.wf_alias.as_deref().unwrap_or_default(),
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                action,
+            })
+        } else {
+            Err(anyhow!("Not a valid collections command"))
+        }
+    }
+}
+
+impl CollectionsCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+
+        match self.action {
+            CollectionsAction::List { format } => {
+                let collections = wf_client.list_collections().await?;
+                reporter.collections(&collections, *format)
+            }
+            CollectionsAction::Create {
+                alias,
+                title,
+                description,
+                if_not_exists: true,
+            } => {
+                let description = description.as_deref();
+                match wf_client
+                    .create_collection_if_missing(alias, title, description)
+                    .await?
+                {
+                    true => reporter
+                        .message(&format!("Created collection: {} [title={}]", alias, title)),
+                    false => reporter.message(&format!("Collection already exists: {}", alias)),
+                }
+            }
+            CollectionsAction::Create {
+                alias,
+                title,
+                description,
+                if_not_exists: false,
+            } => {
+                wf_client
+                    .create_collection(alias, title, description.as_deref())
+                    .await?;
+                reporter.message(&format!("Created collection: {} [title={}]", alias, title))
+            }
+        }
+    }
+}
+
+pub(crate) fn render_collections(
+    collections: &[CollectionInfo],
+    format: CollectionsFormat,
+) -> Result<String> {
+    let output = match format {
+        CollectionsFormat::Json => serde_json::to_string_pretty(collections)? + "\n",
+        CollectionsFormat::Table => {
+            let rows: Vec<_> = collections
+                .iter()
+                .map(|c| [c.alias.clone(), c.title.clone(), c.total_posts.to_string()])
+                .collect();
+            render_table(["alias", "title", "posts"], &rows)
+        }
+    };
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn collections() -> Vec<CollectionInfo> {
+        vec![
+            CollectionInfo {
+                alias: "blog".to_string(),
+                title: "My Blog".to_string(),
+                total_posts: 12,
+            },
+            CollectionInfo {
+                alias: "notes".to_string(),
+                title: "Notes".to_string(),
+                total_posts: 3,
+            },
+        ]
+    }
+
+    #[test]
+    fn try_from_cli_list() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "collections",
+            "--wf-url",
+            "https://write.example.com",
+            "list",
+            "--format",
+            "json",
+        ]);
+
+        let cmd = CollectionsCommand::try_from(&cli)?;
+        assert_eq!(cmd.wf_url, "https://write.example.com");
+        assert!(matches!(
+            cmd.action,
+            CollectionsAction::List {
+                format: CollectionsFormat::Json
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_cli_create() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "collections",
+            "--wf-url",
+            "https://write.example.com",
+            "create",
+            "--alias",
+            "notes",
+            "--title",
+            "Notes",
+        ]);
+
+        let cmd = CollectionsCommand::try_from(&cli)?;
+        match cmd.action {
+            CollectionsAction::Create {
+                alias,
+                title,
+                description,
+                if_not_exists,
+            } => {
+                assert_eq!(alias, "notes");
+                assert_eq!(title, "Notes");
+                assert_eq!(description, &None);
+                assert!(!if_not_exists);
+            }
+            other => panic!("Expected create action, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn create_requires_alias() {
+        let result = Cli::try_parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "collections",
+            "--wf-url",
+            "https://write.example.com",
+            "create",
+            "--title",
+            "Notes",
+        ]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn table_output_has_post_counts() -> Result<()> {
+        let output = render_collections(&collections(), CollectionsFormat::Table)?;
+        assert_eq!(
+            output,
+            "alias  title    posts\n\
+             blog   My Blog  12\n\
+             notes  Notes    3\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_output_has_post_counts() -> Result<()> {
+        let output = render_collections(&collections(), CollectionsFormat::Json)?;
+        let json: serde_json::Value = serde_json::from_str(&output)?;
+        assert_eq!(json[1]["alias"], "notes");
+        assert_eq!(json[1]["total_posts"], 3);
+        Ok(())
+    }
+}
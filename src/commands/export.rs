@@ -0,0 +1,353 @@
+use crate::output::Reporter;
+use crate::wf::{WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use tracing::{error, info};
+use url::Url;
+use writefreely_client::post::Post;
+
+/// Inline Markdown links and images.
+static MARKDOWN_LINK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"!?\[([^\]]*)\]\(([^)\s]+)(?:\s+"[^"]*")?\)"#).unwrap());
+
+/// File format of exported posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ExportFormat {
+    /// The post as WriteFreely stores it.
+    Markdown,
+
+    /// The post converted to Gemtext, on a best-effort basis.
+    Gemtext,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Gemtext => "gmi",
+        }
+    }
+}
+
+pub(crate) struct ExportCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    output_dir: &'a Path,
+    format: ExportFormat,
+    overwrite: bool,
+}
+
+impl<'a> TryFrom<&'a Cli> for ExportCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::Export {
+            ref wf_url,
+            ref output_dir,
+            format,
+            overwrite,
+        }) = cli.command
+        {
+            let wf_alias = cli
+                .wf_alias
+                .as_deref()
+                .ok_or(anyhow!("WriteFreely alias required"))?;
+
+            Ok(Self {
+                wf_url,
+                wf_alias,
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                output_dir,
+                format,
+                overwrite,
+            })
+        } else {
+            Err(anyhow!("Not a valid export command"))
+        }
+    }
+}
+
+impl ExportCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+        let posts = wf_client.get_all_posts().await?;
+        fs::create_dir_all(self.output_dir)?;
+
+        let (mut written, mut failed) = (0, 0);
+        for post in &posts {
+            match self.export_post(post) {
+                Ok(Some(path)) => {
+                    info!("Exported post: {}", path.display());
+                    written += 1;
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    error!("Error exporting post {}: {}", post.id, err);
+                    failed += 1;
+                }
+            }
+        }
+
+        reporter.message(&format!(
+            "Export complete [posts written={}/{}, dir={}]",
+            written,
+            posts.len(),
+            self.output_dir.display()
+        ))?;
+
+        match failed {
+            0 => Ok(()),
+            _ => Err(anyhow!("Could not export {} posts", failed)),
+        }
+    }
+
+    /// Write one post, returning its path, or None if the file already
+    /// exists and is not to be overwritten.
+    fn export_post(&self, post: &Post) -> Result<Option<PathBuf>> {
+        let slug = post.slug.as_ref().map(|slug| slug.to_string());
+        let name = export_file_name(slug.as_deref().unwrap_or(&post.id.to_string()), self.format)?;
+        let path = self.output_dir.join(name);
+
+        let markdown = post_file_markdown(post.title.as_deref(), &post.body);
+        let contents = match self.format {
+            ExportFormat::Markdown => markdown,
+            ExportFormat::Gemtext => markdown_to_gemtext(&markdown),
+        };
+
+        match write_export(&path, &contents, self.overwrite)? {
+            true => Ok(Some(path)),
+            false => {
+                info!("Skipped (file exists): {}", path.display());
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// `<slug>.md` or `<slug>.gmi`. Slugs that could point outside the
+/// output directory are refused.
+fn export_file_name(slug: &str, format: ExportFormat) -> Result<String> {
+    if slug.is_empty() || slug.starts_with('.') || slug.contains(['/', '\\']) {
+        return Err(anyhow!("Slug cannot be used as a file name: {:?}", slug));
+    }
+
+    Ok(format!("{}.{}", slug, format.extension()))
+}
+
+/// Write the file, unless it exists and overwrite is false. Returns
+/// whether the file was written.
+fn write_export(path: &Path, contents: &str, overwrite: bool) -> Result<bool> {
+    let mut options = OpenOptions::new();
+    match overwrite {
+        true => options.write(true).create(true).truncate(true),
+        false => options.write(true).create_new(true),
+    };
+
+    match options.open(path) {
+        Ok(mut file) => {
+            file.write_all(contents.as_bytes())?;
+            Ok(true)
+        }
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// The post body with its title as a top-level heading, as the body
+/// stored by WriteFreely does not include the title.
+fn post_file_markdown(title: Option<&str>, body: &str) -> String {
+    let body = body.trim_end();
+    match title.map(str::trim).filter(|title| !title.is_empty()) {
+        Some(title) => format!("# {}\n\n{}\n", title, body),
+        None => format!("{}\n", body),
+    }
+}
+
+/// Best-effort conversion of Markdown to Gemtext. Headings deeper
+/// than ### become ###, `-` and `+` list items become `*` items, bold
+/// markers are dropped, and inline links are listed as link lines
+/// after their paragraph. Anything else is kept as text.
+fn markdown_to_gemtext(markdown: &str) -> String {
+    let mut gemtext = String::with_capacity(markdown.len());
+    let mut links: Vec<String> = vec![];
+    let mut preformatted = false;
+
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            preformatted = !preformatted;
+        }
+
+        if preformatted || line.starts_with("```") {
+            gemtext.push_str(line);
+            gemtext.push('\n');
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            flush_links(&mut gemtext, &mut links);
+            gemtext.push('\n');
+            continue;
+        }
+
+        // A line of nothing but one link is a link line already.
+        if let Some(link) = MARKDOWN_LINK_REGEX.captures(line.trim()) {
+            if link[0].len() == line.trim().len() {
+                gemtext.push_str(&link_line(&link[2], &link[1]));
+                gemtext.push('\n');
+                continue;
+            }
+        }
+
+        links.extend(
+            MARKDOWN_LINK_REGEX
+                .captures_iter(line)
+                .map(|link| link_line(&link[2], &link[1])),
+        );
+
+        let text = MARKDOWN_LINK_REGEX.replace_all(line, "$1");
+        let text = text.replace("**", "").replace("__", "");
+        gemtext.push_str(&gemtext_line(&text));
+        gemtext.push('\n');
+    }
+
+    flush_links(&mut gemtext, &mut links);
+    gemtext
+}
+
+fn gemtext_line(line: &str) -> String {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) && line[level..].starts_with(' ') {
+        return format!("{}{}", "#".repeat(level.min(3)), &line[level..]);
+    }
+
+    let trimmed = line.trim_start();
+    match trimmed.strip_prefix("- ").or(trimmed.strip_prefix("+ ")) {
+        Some(item) => format!("* {}", item),
+        None => line.to_owned(),
+    }
+}
+
+fn link_line(url: &str, text: &str) -> String {
+    format!("=> {} {}", url, text.trim()).trim_end().to_owned()
+}
+
+fn flush_links(gemtext: &mut String, links: &mut Vec<String>) {
+    for link in links.drain(..) {
+        gemtext.push_str(&link);
+        gemtext.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn temp_dir(name: &str) -> Result<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("gemfreely-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    #[test]
+    fn try_from_cli() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "export",
+            "--wf-url",
+            "https://write.example.com",
+            "--output-dir",
+            "backup",
+            "--format",
+            "gemtext",
+        ]);
+
+        let cmd = ExportCommand::try_from(&cli)?;
+        assert_eq!(cmd.output_dir, Path::new("backup"));
+        assert_eq!(cmd.format, ExportFormat::Gemtext);
+        assert!(!cmd.overwrite);
+        Ok(())
+    }
+
+    #[test]
+    fn file_names_use_slug_and_format() -> Result<()> {
+        assert_eq!(
+            export_file_name("my-post", ExportFormat::Markdown)?,
+            "my-post.md"
+        );
+        assert_eq!(
+            export_file_name("my-post", ExportFormat::Gemtext)?,
+            "my-post.gmi"
+        );
+        assert!(export_file_name("../etc/passwd", ExportFormat::Markdown).is_err());
+        assert!(export_file_name("", ExportFormat::Markdown).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn existing_files_are_skipped_without_overwrite() -> Result<()> {
+        let dir = temp_dir("export-skip")?;
+        let path = dir.join("post.md");
+        fs::write(&path, "old")?;
+
+        let written = write_export(&path, "new", false)?;
+        let contents = fs::read_to_string(&path)?;
+        fs::remove_dir_all(&dir)?;
+
+        assert!(!written);
+        assert_eq!(contents, "old");
+        Ok(())
+    }
+
+    #[test]
+    fn existing_files_are_replaced_with_overwrite() -> Result<()> {
+        let dir = temp_dir("export-overwrite")?;
+        let path = dir.join("post.md");
+        fs::write(&path, "old contents")?;
+
+        let written = write_export(&path, "new", true)?;
+        let contents = fs::read_to_string(&path)?;
+        fs::remove_dir_all(&dir)?;
+
+        assert!(written);
+        assert_eq!(contents, "new");
+        Ok(())
+    }
+
+    #[test]
+    fn title_becomes_heading() {
+        assert_eq!(
+            post_file_markdown(Some("My Post"), "Text\n\n"),
+            "# My Post\n\nText\n"
+        );
+        assert_eq!(post_file_markdown(Some(""), "Text"), "Text\n");
+    }
+
+    #[test]
+    fn markdown_to_gemtext_best_effort() {
+        let markdown =
+            "# Title\n\n#### Deep\n\nSee [this post](https://example.com/a) and **that**.\n\
+                        - one\n+ two\n\n[Home](gemini://example.com/)\n\n\
+                        ```rust\n- not a list\n```\n";
+
+        assert_eq!(
+            markdown_to_gemtext(markdown),
+            "# Title\n\n### Deep\n\nSee this post and that.\n* one\n* two\n\
+             => https://example.com/a this post\n\n=> gemini://example.com/ Home\n\n\
+             ```rust\n- not a list\n```\n"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::output::Reporter;
+use crate::wf::{WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+use std::result::Result as StdResult;
+use url::Url;
+
+pub(crate) struct GenerateGemfeedCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    output: Option<&'a Path>,
+}
+
+impl<'a> TryFrom<&'a Cli> for GenerateGemfeedCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::GenerateGemfeed {
+            ref wf_url,
+            ref output,
+        }) = cli.command
+        {
+            let wf_alias = cli
+                .wf_alias
+                .as_deref()
+                .ok_or(anyhow!("WriteFreely alias required"))?;
+
+            Ok(Self {
+                wf_url,
+                wf_alias,
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                output: output.as_deref(),
+            })
+        } else {
+            Err(anyhow!("Not a valid generate-gemfeed command"))
+        }
+    }
+}
+
+impl GenerateGemfeedCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+        let gemfeed = wf_client.collection_posts_as_gemfeed().await?;
+
+        match self.output {
+            Some(path) => {
+                fs::write(path, gemfeed)?;
+                reporter.message(&format!("Wrote Gemfeed to {}", path.display()))
+            }
+            None => reporter.message(gemfeed.trim_end()),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::output::Reporter;
+use crate::wf::{timestamp_to_datetime, WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::result::Result as StdResult;
+use url::Url;
+use writefreely_client::post::Post;
+
+/// Output format of the list command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ListFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+pub(crate) struct ListCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    format: ListFormat,
+}
+
+impl<'a> TryFrom<&'a Cli> for ListCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::List { ref wf_url, format }) = cli.command {
+            let wf_alias = cli
+                .wf_alias
+                .as_deref()
+                .ok_or(anyhow!("WriteFreely alias required"))?;
+
+            Ok(Self {
+                wf_url,
+                wf_alias,
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                format,
+            })
+        } else {
+            Err(anyhow!("Not a valid list command"))
+        }
+    }
+}
+
+impl ListCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+        let posts = wf_client.posts().await?;
+        reporter.posts(&posts, self.format)
+    }
+}
+
+/// The slug, title and creation date of every post.
+fn post_rows(posts: &[Post]) -> Result<Vec<[String; 3]>> {
+    posts
+        .iter()
+        .map(|post| {
+            let created = post
+                .created
+                .as_ref()
+                .map(timestamp_to_datetime)
+                .transpose()?
+                .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string());
+
+            Ok([
+                post.slug
+                    .as_ref()
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                post.title.clone().unwrap_or_default(),
+                created.unwrap_or_default(),
+            ])
+        })
+        .collect()
+}
+
+pub(crate) fn render_posts(posts: &[Post], format: ListFormat) -> Result<String> {
+    const HEADER: [&str; 3] = ["slug", "title", "created"];
+
+    let output = match format {
+        ListFormat::Json => serde_json::to_string_pretty(posts)? + "\n",
+        ListFormat::Csv => {
+            let mut csv = HEADER.join(",") + "\n";
+            for row in post_rows(posts)? {
+                let fields: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+        ListFormat::Table => render_table(HEADER, &post_rows(posts)?),
+    };
+
+    Ok(output)
+}
+
+/// Three columns, aligned on the widest field of each.
+pub(crate) fn render_table(header: [&str; 3], rows: &[[String; 3]]) -> String {
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, field) in widths.iter_mut().zip(row) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let header = header.map(String::from);
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(rows) {
+        let line = format!(
+            "{:w0$}  {:w1$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            w0 = widths[0],
+            w1 = widths[1]
+        );
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn posts() -> Result<Vec<Post>> {
+        let json = r#"[
+            {"id": "a", "slug": "first-post", "title": "First, post", "body": "Text",
+             "appearance": "norm", "language": "en", "rtl": false, "tags": [],
+             "views": 0, "created": "2024-01-05T10:00:00Z"}
+        ]"#;
+        Ok(serde_json::from_str(json)?)
+    }
+
+    #[test]
+    fn try_from_cli() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "list",
+            "--wf-url",
+            "https://write.example.com",
+            "--format",
+            "csv",
+        ]);
+
+        let cmd = ListCommand::try_from(&cli)?;
+        assert_eq!(cmd.wf_url, "https://write.example.com");
+        assert_eq!(cmd.wf_alias, "blog");
+        assert_eq!(cmd.format, ListFormat::Csv);
+        Ok(())
+    }
+
+    #[test]
+    fn json_output_contains_slug() -> Result<()> {
+        let output = render_posts(&posts()?, ListFormat::Json)?;
+        let json: serde_json::Value = serde_json::from_str(&output)?;
+        assert_eq!(json[0]["slug"], "first-post");
+        Ok(())
+    }
+
+    #[test]
+    fn csv_output_quotes_fields() -> Result<()> {
+        let output = render_posts(&posts()?, ListFormat::Csv)?;
+        assert_eq!(
+            output,
+            "slug,title,created\nfirst-post,\"First, post\",2024-01-05 10:00:00\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn table_output_aligns_columns() -> Result<()> {
+        let output = render_posts(&posts()?, ListFormat::Table)?;
+        assert_eq!(
+            output,
+            "slug        title        created\n\
+             first-post  First, post  2024-01-05 10:00:00\n"
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    output::Reporter,
     wf::{WriteFreely, WriteFreelyCredentials},
     Cli, Command,
 };
@@ -40,16 +41,11 @@ impl<'a> From<&'a LoginCommand<'a>> for WriteFreelyCredentials<'a> {
 }
 
 impl LoginCommand<'_> {
-    pub async fn execute(self) -> Result<()> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
         let wf_url = Url::parse(self.wf_url)?;
         let creds = WriteFreelyCredentials::from(&self);
-        let wf_client = WriteFreely::new(&wf_url, &self.username, &creds).await?;
+        let wf_client = WriteFreely::new(&wf_url, self.username, &creds).await?;
 
-        println!(
-            "{}",
-            wf_client.access_token().unwrap_or("[No Token Returned]")
-        );
-
-        Ok(())
+        reporter.token(wf_client.access_token().unwrap_or("[No Token Returned]"))
     }
 }
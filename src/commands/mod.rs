@@ -1,3 +1,95 @@
-pub(crate) mod sync;
+pub(crate) mod capsule_info;
+pub(crate) mod collections;
+pub(crate) mod export;
+pub(crate) mod generate_gemfeed;
+pub(crate) mod list;
 pub(crate) mod login;
 pub(crate) mod logout;
+pub(crate) mod move_posts;
+pub(crate) mod publish;
+pub(crate) mod rename_post;
+pub(crate) mod sync;
+
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+/// Read a WriteFreely access token from a file, so that it does not
+/// end up in shell history or the process list. Surrounding
+/// whitespace is removed.
+pub(crate) fn read_token_file(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| anyhow!("Could not read token file {}: {}", path.display(), err))?;
+
+    if is_world_readable(path)? {
+        warn!("Token file {} can be read by every user", path.display());
+    }
+
+    match contents.trim() {
+        "" => Err(anyhow!("Token file is empty: {}", path.display())),
+        token => Ok(token.to_owned()),
+    }
+}
+
+#[cfg(unix)]
+fn is_world_readable(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(path)?.mode() & 0o004 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_world_readable(_path: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gemfreely-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn token_is_trimmed() -> Result<()> {
+        let path = temp_path("token-trimmed");
+        fs::write(&path, "  secret-token\n")?;
+
+        let token = read_token_file(&path);
+        fs::remove_file(&path)?;
+        assert_eq!(token?, "secret-token");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn world_readable_token_file() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("token-world-readable");
+        fs::write(&path, "secret-token")?;
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644))?;
+        let world_readable = is_world_readable(&path)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        let private = is_world_readable(&path)?;
+
+        // Still read, with only a warning.
+        let token = read_token_file(&path);
+        fs::remove_file(&path)?;
+
+        assert!(world_readable);
+        assert!(!private);
+        assert_eq!(token?, "secret-token");
+        Ok(())
+    }
+
+    #[test]
+    fn missing_token_file() {
+        let path = temp_path("token-missing");
+        let err = read_token_file(&path).unwrap_err().to_string();
+        assert!(err.contains(&path.display().to_string()));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::output::Reporter;
+use crate::wf::{WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+use std::result::Result as StdResult;
+use tracing::{error, info};
+use url::Url;
+
+pub(crate) struct MovePostsCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    csv_file: &'a Path,
+}
+
+impl<'a> TryFrom<&'a Cli> for MovePostsCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::MovePosts {
+            ref wf_url,
+            ref csv_file,
+        }) = cli.command
+        {
+            Ok(Self {
+                wf_url,
+                wf_alias: cli.wf_alias.as_deref().unwrap_or_default(),
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                csv_file,
+            })
+        } else {
+            Err(anyhow!("Not a valid move-posts command"))
+        }
+    }
+}
+
+impl MovePostsCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let moves = parse_moves(&fs::read_to_string(self.csv_file)?)?;
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+
+        let mut count = 0;
+        for (post_id, alias) in &moves {
+            match wf_client.move_post_to_collection(post_id, alias).await {
+                Ok(_) => {
+                    info!("Moved post: {} [collection={}]", post_id, alias);
+                    count += 1;
+                }
+                Err(err) => error!("Error moving post {}: {}", post_id, err),
+            }
+        }
+
+        reporter.message(&format!(
+            "Post move complete [posts moved={}/{}]",
+            count,
+            moves.len()
+        ))
+    }
+}
+
+/// Parse `post_id,target_alias` lines. Blank lines and an optional
+/// `post_id,target_alias` header are skipped.
+fn parse_moves(csv: &str) -> Result<Vec<(String, String)>> {
+    csv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && *line != "post_id,target_alias")
+        .map(|line| match line.split_once(',') {
+            Some((id, alias)) if !id.trim().is_empty() && !alias.trim().is_empty() => {
+                Ok((id.trim().to_owned(), alias.trim().to_owned()))
+            }
+            _ => Err(anyhow!("Invalid move-posts line: {}", line)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_moves_with_header() -> Result<()> {
+        let csv = "post_id,target_alias\nabc123, blog\n\ndef456,other\n";
+        let moves = parse_moves(csv)?;
+        assert_eq!(
+            moves,
+            vec![
+                ("abc123".to_string(), "blog".to_string()),
+                ("def456".to_string(), "other".to_string())
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_moves_rejects_bad_lines() {
+        assert!(parse_moves("abc123\n").is_err());
+        assert!(parse_moves("abc123,\n").is_err());
+    }
+}
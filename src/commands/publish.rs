@@ -0,0 +1,51 @@
+use crate::output::Reporter;
+use crate::wf::{WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use std::result::Result as StdResult;
+use url::Url;
+
+pub(crate) struct PublishCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    slug: &'a str,
+}
+
+impl<'a> TryFrom<&'a Cli> for PublishCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::Publish {
+            ref wf_url,
+            ref slug,
+        }) = cli.command
+        {
+            let wf_alias = cli
+                .wf_alias
+                .as_deref()
+                .ok_or(anyhow!("WriteFreely alias required"))?;
+
+            Ok(Self {
+                wf_url,
+                wf_alias,
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                slug,
+            })
+        } else {
+            Err(anyhow!("Not a valid publish command"))
+        }
+    }
+}
+
+impl PublishCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+        wf_client.set_draft_status(self.slug, false).await?;
+
+        reporter.message(&format!(
+            "Published post: {} [collection={}]",
+            self.slug, self.wf_alias
+        ))
+    }
+}
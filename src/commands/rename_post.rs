@@ -0,0 +1,56 @@
+use crate::output::Reporter;
+use crate::wf::{WriteFreely, WriteFreelyCredentials};
+use crate::{Cli, Command};
+use anyhow::{anyhow, Result};
+use std::result::Result as StdResult;
+use url::Url;
+
+pub(crate) struct RenamePostCommand<'a> {
+    wf_url: &'a str,
+    wf_alias: &'a str,
+    wf_creds: WriteFreelyCredentials<'a>,
+    from_slug: &'a str,
+    to_slug: &'a str,
+}
+
+impl<'a> TryFrom<&'a Cli> for RenamePostCommand<'a> {
+    type Error = anyhow::Error;
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        if let Some(Command::RenamePost {
+            ref wf_url,
+            ref from_slug,
+            ref to_slug,
+        }) = cli.command
+        {
+            let wf_alias = cli
+                .wf_alias
+                .as_deref()
+                .ok_or(anyhow!("WriteFreely alias required"))?;
+
+            Ok(Self {
+                wf_url,
+                wf_alias,
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
+                from_slug,
+                to_slug,
+            })
+        } else {
+            Err(anyhow!("Not a valid rename-post command"))
+        }
+    }
+}
+
+impl RenamePostCommand<'_> {
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        let wf_url = Url::parse(self.wf_url)?;
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds).await?;
+        let post = wf_client
+            .rename_post_slug(self.from_slug, self.to_slug)
+            .await?;
+
+        reporter.message(&format!(
+            "Renamed post: {} [{} -> {}]",
+            post.id, self.from_slug, self.to_slug
+        ))
+    }
+}
@@ -1,25 +1,108 @@
 use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use regex::Regex;
+use tracing::{error, info, warn};
 use url::Url;
 
-use crate::gemfeed::{Gemfeed, GemfeedParserSettings};
-use crate::sanitization;
-use crate::wf::{WriteFreely, WriteFreelyCredentials};
+use crate::change_detection::{content_hash, HashStore};
+use crate::gemfeed::{Gemfeed, GemfeedEntry, GemfeedParserSettings};
+use crate::output::{Reporter, SyncReport};
+use crate::persistence::{SyncState, SyncStateFile};
+use crate::sanitization::{self, SanitizationPipeline};
+use crate::tracking::PostIdTracker;
+use crate::wf::{self, WriteFreely, WriteFreelyCredentials};
 use crate::Cli;
 use crate::Command;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
 
+/// Which way posts flow during a sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SyncDirection {
+    /// Create WriteFreely posts from the Gemlog.
+    GeminiToWf,
+
+    /// Create Gemlog posts from WriteFreely. Not yet implemented.
+    WfToGemini,
+}
+
+/// Order in which new posts are created. Posts without a publish date
+/// always come last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum SyncOrder {
+    NewestFirst,
+
+    /// Gives WriteFreely a chronological timeline when migrating a
+    /// whole Gemlog.
+    OldestFirst,
+
+    /// The order of the posts in the Gemlog feed.
+    FeedOrder,
+}
+
+#[derive(Default)]
 struct SanitizeConfig<'a> {
-    strip_before_marker: &'a Option<String>,
-    strip_after_marker: &'a Option<String>,
+    extract_frontmatter: bool,
+    tag_heading_level: Option<usize>,
+    strip_before_markers: &'a [String],
+    strip_after_markers: &'a [String],
+    strip_between_markers: Option<(&'a str, &'a str)>,
+    truncate_to_words: Option<usize>,
+    truncate_suffix: &'a str,
+    links_section_marker: Option<&'a str>,
+    strip_gemtext_comments: bool,
+    normalize_headings: bool,
+    linkify_bare_urls: bool,
+    collapse_blank_lines: bool,
+    strip_lines_matching: Vec<Regex>,
+    replace_patterns: Vec<(Regex, String)>,
+    summary_max_chars: Option<usize>,
+    max_post_bytes: Option<usize>,
 }
 
 pub(crate) struct SyncCommand<'a> {
     parser_settings: GemfeedParserSettings<'a>,
     wf_alias: &'a str,
-    wf_token: &'a str,
-    gemlog_url: &'a str,
+    wf_collection: &'a str,
+    create_collection_if_missing: bool,
+    wf_creds: WriteFreelyCredentials<'a>,
+    gemlog_urls: &'a [String],
+    opml_file: Option<&'a Path>,
+    gemlog_url_file: Option<&'a Path>,
     wf_url: &'a str,
     config: SanitizeConfig<'a>,
+    filter_title_regex: Option<&'a str>,
+    exclude_title_regex: Option<&'a str>,
+    republish_as_new: bool,
+    override_publish_date: Option<DateTime<Utc>>,
+    post_language: Option<&'a str>,
+    no_body: bool,
+    direction: SyncDirection,
+    post_ids: Option<PostIdTracker>,
+    state_file: Option<SyncStateFile>,
+    hash_store: Option<HashStore>,
+    collect_errors: bool,
+    dry_run: bool,
+    update_changed: bool,
+    purge: bool,
+    since_date: Option<DateTime<Utc>>,
+    max_age_days: Option<u32>,
+    skip_undated: bool,
+    skip_slugs: HashSet<String>,
+    order: SyncOrder,
+    limit: Option<usize>,
+    publish_as_draft: bool,
+    draft_older_than: Option<u32>,
+    feed_snapshot_cache: Option<&'a Path>,
+    cache_max_age: Duration,
+    wf_concurrency: usize,
+    concurrency: usize,
+    max_retries: u32,
+    verbose: u8,
 }
 
 impl<'a> TryFrom<&'a Cli> for SyncCommand<'a> {
@@ -28,28 +111,157 @@ impl<'a> TryFrom<&'a Cli> for SyncCommand<'a> {
     fn try_from(cli: &'a Cli) -> std::prelude::v1::Result<Self, Self::Error> {
         if let Some(Command::Sync {
             ref wf_url,
+            ref wf_collection,
+            sync_create_if_missing,
             ref gemlog_url,
+            ref opml_file,
+            ref gemlog_url_file,
+            extract_frontmatter,
+            extract_tags_from_heading_level,
             ref strip_before_marker,
             ref strip_after_marker,
+            ref strip_between_start,
+            ref strip_between_end,
+            truncate_to_words,
+            ref truncate_suffix,
+            strip_links_section,
+            ref strip_links_section_marker,
+            strip_gemtext_comments,
+            normalize_headings,
+            linkify_bare_urls,
+            collapse_blank_lines,
+            ref strip_lines_matching,
+            ref replace_pattern,
+            summary_only,
+            summary_max_chars,
+            max_post_bytes,
+            ref filter_title_regex,
+            ref exclude_title_regex,
+            republish_as_new,
+            override_publish_date,
+            ref post_language,
+            no_body,
+            sync_direction,
+            ref post_id_tracking_file,
+            ref state_file,
+            ref hash_store,
+            collect_errors,
+            dry_run,
+            update_changed,
+            purge,
+            since_date,
+            max_age,
+            skip_undated,
+            ref skip_slug,
+            order,
+            limit,
+            publish_as_draft,
+            draft_older_than,
+            wf_concurrency,
+            concurrency,
+            ref feed_snapshot_cache,
+            cache_max_age_minutes,
+            newest_first_limit,
+            max_feed_entries,
+            ..
         }) = cli.command
         {
-            let wf_token = cli
-                .wf_access_token
-                .as_deref()
-                .ok_or(anyhow!("WriteFreely access token required"))?;
+            // The limit may also come from the config file.
+            if purge && (newest_first_limit.is_some() || max_feed_entries.is_some()) {
+                return Err(anyhow!(
+                    "--purge cannot be used with a limit on the number of Gemlog posts"
+                ));
+            }
+
+            // Each Gemlog would purge the posts of the others.
+            if purge && gemlog_url.len() > 1 {
+                return Err(anyhow!("--purge cannot be used with several Gemlog URLs"));
+            }
+
+            if feed_snapshot_cache.is_some() && gemlog_url.len() > 1 {
+                return Err(anyhow!(
+                    "--feed-snapshot-cache cannot be used with several Gemlog URLs"
+                ));
+            }
 
             let sanitize_cfg = SanitizeConfig {
-                strip_before_marker,
-                strip_after_marker,
+                extract_frontmatter,
+                tag_heading_level: extract_tags_from_heading_level.map(usize::from),
+                strip_before_markers: strip_before_marker,
+                strip_after_markers: strip_after_marker,
+                strip_between_markers: strip_between_start
+                    .as_deref()
+                    .zip(strip_between_end.as_deref()),
+                truncate_to_words,
+                truncate_suffix,
+                links_section_marker: match strip_links_section_marker {
+                    Some(marker) => Some(marker.as_str()),
+                    None if strip_links_section => Some(sanitization::DEFAULT_LINKS_SECTION_MARKER),
+                    None => None,
+                },
+                strip_gemtext_comments,
+                normalize_headings,
+                linkify_bare_urls,
+                collapse_blank_lines,
+                strip_lines_matching: strip_lines_matching
+                    .iter()
+                    .map(|pattern| Regex::new(pattern))
+                    .collect::<Result<_, regex::Error>>()?,
+                replace_patterns: replace_pattern
+                    .iter()
+                    .map(|arg| parse_replace_pattern(arg))
+                    .collect::<Result<_>>()?,
+                summary_max_chars: Some(summary_max_chars).filter(|_| summary_only),
+                max_post_bytes,
             };
 
+            let wf_alias = cli.wf_alias.as_deref().expect("WriteFreely Alias required");
+
             Ok(Self {
-                wf_url,
-                gemlog_url,
-                wf_token,
+                wf_url: wf_url
+                    .as_deref()
+                    .ok_or(anyhow!("WriteFreely URL required"))?,
+                gemlog_urls: gemlog_url,
+                opml_file: opml_file.as_deref(),
+                gemlog_url_file: gemlog_url_file.as_deref(),
+                wf_creds: WriteFreelyCredentials::try_from(cli)?,
                 config: sanitize_cfg,
-                parser_settings: GemfeedParserSettings::from(cli),
-                wf_alias: cli.wf_alias.as_deref().expect("WriteFreely Alias required"),
+                filter_title_regex: filter_title_regex.as_deref(),
+                exclude_title_regex: exclude_title_regex.as_deref(),
+                republish_as_new,
+                override_publish_date: override_publish_date
+                    .and_then(|date| date.and_hms_opt(12, 0, 0))
+                    .map(|date| date.and_utc()),
+                post_language: post_language.as_deref(),
+                no_body,
+                direction: sync_direction,
+                post_ids: post_id_tracking_file.as_ref().map(PostIdTracker::new),
+                state_file: state_file.as_ref().map(SyncStateFile::new),
+                hash_store: hash_store.as_ref().map(HashStore::new),
+                collect_errors,
+                dry_run,
+                update_changed,
+                purge,
+                since_date: since_date
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map(|date| date.and_utc()),
+                max_age_days: max_age,
+                skip_undated,
+                skip_slugs: skip_slug.iter().cloned().collect(),
+                order,
+                limit,
+                publish_as_draft,
+                draft_older_than,
+                feed_snapshot_cache: feed_snapshot_cache.as_deref(),
+                cache_max_age: Duration::from_secs(cache_max_age_minutes * 60),
+                wf_concurrency,
+                concurrency,
+                parser_settings: GemfeedParserSettings::try_from(cli)?,
+                wf_alias,
+                wf_collection: wf_collection.as_deref().unwrap_or(wf_alias),
+                create_collection_if_missing: sync_create_if_missing,
+                max_retries: cli.max_retries,
+                verbose: cli.verbose,
             })
         } else {
             Err(anyhow!("Invalid sync command"))
@@ -58,71 +270,1652 @@ impl<'a> TryFrom<&'a Cli> for SyncCommand<'a> {
 }
 
 impl SyncCommand<'_> {
-    pub async fn execute(self) -> Result<()> {
-        let gemfeed_url = Url::parse(self.gemlog_url)?;
+    pub async fn execute(self, reporter: &dyn Reporter) -> Result<()> {
+        if self.direction == SyncDirection::WfToGemini {
+            return Err(anyhow!(
+                "Syncing from WriteFreely to Gemini is not yet implemented"
+            ));
+        }
+
+        let gemfeed_urls = match (self.gemlog_urls, self.opml_file, self.gemlog_url_file) {
+            ([], Some(path), _) => Gemfeed::urls_from_opml(path)?,
+            ([], None, Some(path)) => vec![read_gemlog_url_file(path)?],
+            ([], None, None) => return Err(anyhow!("Gemlog URL, URL file or OPML file required")),
+            (urls, _, _) => urls
+                .iter()
+                .map(|url| Url::parse(url))
+                .collect::<Result<_, _>>()?,
+        };
+
         let wf_url = Url::parse(self.wf_url)?;
 
-        let wf_creds = WriteFreelyCredentials::AccessToken(self.wf_token);
-        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &wf_creds).await?;
+        if let Some(state) = self.state_file.as_ref().map(|f| f.load()).transpose()? {
+            print_state(state.as_ref());
+        }
+
+        let wf_client = WriteFreely::new(&wf_url, self.wf_alias, &self.wf_creds)
+            .await?
+            .with_collection(self.wf_collection)
+            .with_max_retries(self.max_retries);
+
+        if self.create_collection_if_missing && !self.dry_run {
+            let collection = self.wf_collection;
+            if wf_client
+                .create_collection_if_missing(collection, collection, None)
+                .await?
+            {
+                info!("Created collection: {}", collection);
+            }
+        }
+
+        if self.no_body {
+            warn!("--no-body is set, posts will be created without bodies");
+        }
+
+        // With several Gemlogs, one that can't be loaded doesn't stop
+        // the others from syncing.
+        let mut report = SyncReport::default();
+        let lenient = self.collect_errors || gemfeed_urls.len() > 1;
+        let mut gemfeeds = vec![];
+        for url in &gemfeed_urls {
+            match self.load_gemfeed(url).await {
+                Ok(gemfeed) => gemfeeds.push(gemfeed),
+                Err(err) if lenient => {
+                    warn!("Skipping Gemlog {}: {}", url, err);
+                    report.errors.push(format!("Loading {}: {}", url, err));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut synced_per_feed = vec![];
+        for mut gemfeed in gemfeeds {
+            // Taken before filtering, so filtered posts aren't purged.
+            let gemfeed_slugs: HashSet<_> = gemfeed.slugs().into_iter().collect();
+
+            if let Some(pattern) = self.filter_title_regex {
+                gemfeed = gemfeed.filter_by_title_regex(pattern)?;
+            }
+
+            if let Some(pattern) = self.exclude_title_regex {
+                gemfeed = gemfeed.exclude_by_title_regex(pattern)?;
+            }
+
+            if self.no_body {
+                for entry in gemfeed.entries_mut() {
+                    entry.set_body(String::new());
+                }
+            }
+
+            if let Some(date) = self.post_date_override() {
+                for entry in gemfeed.entries_mut() {
+                    entry.set_republish_date(Some(date));
+                }
+            }
+
+            // Frontmatter, applied later, still takes precedence.
+            if let Some(language) = self.post_language {
+                for entry in gemfeed.entries_mut() {
+                    if entry.language().is_none() {
+                        entry.set_language(Some(language.to_owned()));
+                    }
+                }
+            }
+
+            if self.verbose > 0 {
+                print_statistics(&gemfeed);
+            }
+
+            let count = self
+                .sync_gemlog(&mut gemfeed, &wf_client, &mut report)
+                .await?;
+            synced_per_feed.push((gemfeed.url().clone(), count));
+
+            if self.purge {
+                self.purge_posts(&wf_client, &gemfeed_slugs, &mut report)
+                    .await?;
+            }
+        }
+
+        if gemfeed_urls.len() > 1 {
+            info!("Posts synced per Gemlog:");
+            for (url, count) in &synced_per_feed {
+                info!("  {}: {}", url, count);
+            }
+        }
+
+        reporter.sync_report(&report)?;
+
+        if self.collect_errors && !report.errors.is_empty() {
+            return Err(combine_errors(&report.errors));
+        }
+
+        Ok(())
+    }
+
+    /// Whether a new post for the entry goes to the drafts, because of
+    /// --publish-as-draft or --draft-older-than.
+    fn is_draft(&self, entry: &GemfeedEntry) -> bool {
+        let too_old = match (self.draft_older_than, entry.age_days()) {
+            (Some(max_age), Some(age)) => age > max_age.into(),
+            _ => false,
+        };
+
+        self.publish_as_draft || too_old
+    }
+
+    /// The WriteFreely post date for every new post, from
+    /// --republish-as-new or --override-publish-date. It only replaces
+    /// the post date; filtering still uses the Gemlog dates.
+    fn post_date_override(&self) -> Option<DateTime<Utc>> {
+        match self.republish_as_new {
+            true => Some(Utc::now()),
+            false => self.override_publish_date,
+        }
+    }
+
+    /// Load the Gemlog from the snapshot cache if it is fresh,
+    /// otherwise from Gemini. A fetched Gemlog has its bodies loaded
+    /// concurrently (sanitization needs them anyway) and is saved to
+    /// the cache before any sanitization happens. A body that can't be
+    /// fetched is left out of the snapshot and fetched again if needed.
+    async fn load_gemfeed(&self, url: &Url) -> Result<Gemfeed> {
+        let path = match self.feed_snapshot_cache {
+            Some(path) => path,
+            _ => return Gemfeed::load_with_settings(url, &self.parser_settings),
+        };
+
+        if is_fresh(path, self.cache_max_age) {
+            match Gemfeed::load_snapshot(path, url, &self.parser_settings)? {
+                Some(gemfeed) => {
+                    info!("Loaded Gemlog from snapshot: {}", path.display());
+                    return Ok(gemfeed);
+                }
+                None => info!("Ignoring snapshot of another Gemlog: {}", path.display()),
+            }
+        }
+
+        let mut gemfeed = Gemfeed::load_with_settings(url, &self.parser_settings)?;
+        if !self.no_body {
+            for (slug, err) in gemfeed.preload_bodies(self.concurrency, |_| true).await {
+                warn!("Could not cache body of {}: {}", slug, err);
+            }
+        }
+
+        gemfeed.save_snapshot(path)?;
+        Ok(gemfeed)
+    }
+
+    /// Change the sync state file, if there is one. A failed write
+    /// is reported but does not stop the sync.
+    fn update_state<F: FnOnce(&mut SyncState)>(&self, change: F) {
+        if let Some(state_file) = &self.state_file {
+            if let Err(err) = state_file.update(change) {
+                error!("Error writing sync state file: {}", err);
+            }
+        }
+    }
+
+    /// Delete the collection posts that are not in the Gemlog.
+    async fn purge_posts(
+        &self,
+        wf: &WriteFreely,
+        gemfeed_slugs: &HashSet<String>,
+        report: &mut SyncReport,
+    ) -> Result<()> {
+        let wf_slugs: HashSet<_> = wf.slugs().await?.into_iter().collect();
 
-        let mut gemfeed = Gemfeed::load_with_settings(&gemfeed_url, &self.parser_settings)?;
-        sync_gemlog(&self.config, &mut gemfeed, &wf_client).await?;
+        for slug in slugs_to_purge(&wf_slugs, gemfeed_slugs) {
+            if self.dry_run {
+                info!("Would delete: {}", slug);
+            } else if let Err(err) = wf.delete_post(&slug).await {
+                error!("Error deleting post: {} ", err);
+                report.fail(&slug, "Deleting", err);
+            } else {
+                info!("Deleted post: {}", slug);
+            }
+        }
 
         Ok(())
     }
+
+    async fn sync_gemlog(
+        &self,
+        gemfeed: &mut Gemfeed,
+        wf: &WriteFreely,
+        report: &mut SyncReport,
+    ) -> Result<usize> {
+        info!(
+            "Beginning sync of posts for WriteFreely user: {}",
+            wf.user().await?
+        );
+
+        let wf_slugs: HashSet<_> = wf.slugs().await?.into_iter().collect();
+
+        // Drafts are in no collection, but they exist all the same, even
+        // if created by an earlier sync with other options.
+        let mut existing_slugs = wf_slugs.clone();
+        existing_slugs.extend(wf.draft_slugs().await?);
+        let new_slugs = slugs_not_on_wf(gemfeed, &existing_slugs);
+        let mut slugs_to_post = new_slugs.clone();
+
+        // Posts recorded in the tracking file may have been moved out of
+        // the collection. They still exist by ID, so don't create them again.
+        if let Some(tracker) = &self.post_ids {
+            let recorded = tracker.load()?;
+            let mut already_posted = HashSet::new();
+
+            for slug in &slugs_to_post {
+                if let Some(id) = recorded.get(slug.as_str()) {
+                    if wf.post_exists_by_id(id).await? {
+                        info!("Skipping post already created as {}: {}", id, slug);
+                        already_posted.insert(slug.to_string());
+                    }
+                }
+            }
+
+            slugs_to_post.retain(|slug| !already_posted.contains(slug.as_str()));
+        }
+
+        slugs_to_post.retain(|slug| {
+            gemfeed.find_entry_by_slug(slug).is_some_and(|entry| {
+                is_published_since(entry, self.since_date, self.max_age_days, self.skip_undated)
+            })
+        });
+
+        slugs_to_post.retain(|slug| {
+            let excluded = self.skip_slugs.contains(slug);
+            if excluded {
+                info!("Skipped (excluded by --skip-slug): {}", slug);
+            }
+            !excluded
+        });
+
+        order_slugs(gemfeed, &mut slugs_to_post, self.order);
+        if let Some(limit) = self.limit {
+            slugs_to_post.truncate(limit);
+        }
+
+        let mut skipped: Vec<_> = new_slugs
+            .into_iter()
+            .filter(|slug| !slugs_to_post.contains(slug))
+            .collect();
+        skipped.sort();
+        report.skipped.extend(skipped);
+
+        if self.dry_run {
+            let count = print_dry_run(gemfeed, &slugs_to_post);
+            info!("Dry run complete [would sync {} posts]", count);
+            report.skipped.extend(slugs_to_post);
+            return Ok(0);
+        }
+
+        // Only the posts to create, and those --update-changed compares,
+        // need a body. Entries left without one are dropped.
+        let mut to_fetch: HashSet<_> = slugs_to_post.iter().cloned().collect();
+        if self.update_changed {
+            to_fetch.extend(
+                gemfeed
+                    .slugs()
+                    .into_iter()
+                    .filter(|slug| wf_slugs.contains(slug)),
+            );
+        }
+
+        if self.verbose > 1 {
+            for entry in gemfeed.entries().filter(|e| to_fetch.contains(e.slug())) {
+                info!(
+                    "Fetching body for slug={}, body_loaded={}",
+                    entry.slug(),
+                    entry.has_body_loaded()
+                );
+            }
+        }
+
+        let failures = gemfeed
+            .preload_bodies(self.concurrency, |entry| to_fetch.contains(entry.slug()))
+            .await;
+        for (slug, err) in failures {
+            if !self.collect_errors {
+                return Err(anyhow!("Fetching body of {}: {}", slug, err));
+            }
+
+            error!("Error fetching body for {}: {}", slug, err);
+            report.fail(&slug, "Fetching", err);
+        }
+
+        gemfeed.retain(|entry| entry.has_body_loaded());
+        sanitize_gemlogs(gemfeed, &self.config)?;
+
+        let gemlogs_to_post: Vec<_> = slugs_to_post
+            .into_iter()
+            .flat_map(|slug| gemfeed.find_entry_by_slug(slug))
+            .collect();
+
+        if self.verbose > 1 {
+            for entry in &gemlogs_to_post {
+                info!(
+                    "Posting {} [url={}, lines={}, bytes={}]",
+                    entry.slug(),
+                    entry.permalink(),
+                    entry.body_line_count()?,
+                    entry.body_size_bytes()?
+                );
+            }
+        }
+
+        let posts_to_create: Vec<_> = gemlogs_to_post
+            .into_iter()
+            .map(|entry| (entry, self.is_draft(entry)))
+            .collect();
+
+        let results = if self.wf_concurrency > 1 {
+            wf.bulk_create_posts(&posts_to_create, self.wf_concurrency)
+                .await
+        } else {
+            let mut results = vec![];
+            for &(entry, draft) in &posts_to_create {
+                results.push(wf.create_post(entry, draft).await);
+            }
+            results
+        };
+
+        let synced_before = report.synced.len();
+        let mut count = 0;
+        for ((entry, draft), result) in posts_to_create.into_iter().zip(results) {
+            count += 1;
+
+            if let Ok(post) = result {
+                info!(
+                    "Created {}: {} [title={}]",
+                    if draft { "draft" } else { "post" },
+                    post.id,
+                    post.title.unwrap_or_default()
+                );
+
+                if let Some(tracker) = &self.post_ids {
+                    if let Err(err) = tracker.record(entry.slug(), &post.id.to_string()) {
+                        error!("Error recording post ID for {}: {}", entry.slug(), err);
+                    }
+                }
+
+                self.update_state(|state| state.record_created(entry.slug()));
+                report.synced.push(entry.slug().to_owned());
+
+                if let Some(hash_store) = &self.hash_store {
+                    match wf::post_body(entry) {
+                        Ok(markdown) => {
+                            record_content_hash(Some(hash_store), entry.slug(), &markdown)
+                        }
+                        Err(err) => error!("Error hashing body of {}: {}", entry.slug(), err),
+                    }
+                }
+            } else {
+                let err = result.unwrap_err();
+                error!("Error creating post: {} ", err);
+                report.fail(entry.slug(), "Creating", err);
+                self.update_state(|state| state.record_failed(entry.slug()));
+            }
+        }
+
+        match self.limit {
+            Some(limit) => info!(
+                "Post synchronization complete [posts synced={}, limit={}]",
+                count, limit
+            ),
+            None => info!("Post synchronization complete [posts synced={}]", count),
+        }
+
+        if self.update_changed {
+            update_changed_posts(gemfeed, wf, &wf_slugs, self.hash_store.as_ref(), report).await;
+        }
+
+        Ok(report.synced.len() - synced_before)
+    }
 }
 
-async fn sync_gemlog(
-    config: &SanitizeConfig<'_>,
-    gemfeed: &mut Gemfeed,
+/// Update the WriteFreely posts whose converted Gemlog body no longer
+/// matches the body on the server. With a hash store, the body is
+/// compared with the hash of what was last sent instead, and the post
+/// is only fetched if there is no hash for it yet.
+async fn update_changed_posts(
+    gemfeed: &Gemfeed,
     wf: &WriteFreely,
-) -> Result<()> {
-    println!(
-        "Beginning sync of posts for WriteFreely user: {}",
-        wf.user().await?
-    );
+    wf_slugs: &HashSet<String>,
+    hash_store: Option<&HashStore>,
+    report: &mut SyncReport,
+) {
+    let mut count = 0;
+
+    // Posts without a stored hash are fetched for comparison instead.
+    let stored_hashes = match hash_store.map(HashStore::load).transpose() {
+        Ok(hashes) => hashes.unwrap_or_default(),
+        Err(err) => {
+            error!("Error reading hash store: {}", err);
+            BTreeMap::new()
+        }
+    };
+
+    for entry in gemfeed.entries().filter(|e| wf_slugs.contains(e.slug())) {
+        let result = async {
+            let markdown = wf::post_body(entry)?;
+            let stored_hash = stored_hashes.get(entry.slug());
+            let changed = match stored_hash {
+                Some(stored) => stored.content_hash != content_hash(&markdown),
+                None => body_changed(&markdown, &wf.get_post_by_slug(entry.slug()).await?.body),
+            };
+
+            let post = match changed {
+                true => Some(wf.update_post(entry.slug(), entry).await?),
+                false => None,
+            };
+
+            if post.is_some() || stored_hash.is_none() {
+                record_content_hash(hash_store, entry.slug(), &markdown);
+            }
+
+            Ok::<_, anyhow::Error>(post)
+        };
+
+        match result.await {
+            Ok(Some(post)) => {
+                count += 1;
+                info!(
+                    "Updated post: {} [title={}]",
+                    post.id,
+                    post.title.unwrap_or_default()
+                );
+            }
+            Ok(None) => (),
+            Err(err) => {
+                error!("Error updating post: {} ", err);
+                report.fail(entry.slug(), "Updating", err);
+            }
+        }
+    }
+
+    info!("Post update complete [posts updated={}]", count);
+}
 
-    let wf_slugs: HashSet<_> = wf.slugs().await?.into_iter().collect();
+/// Store the hash of the body sent to WriteFreely, if there is a hash
+/// store. A failed write is reported but does not stop the sync.
+fn record_content_hash(hash_store: Option<&HashStore>, slug: &str, markdown: &str) {
+    if let Some(hash_store) = hash_store {
+        if let Err(err) = hash_store.record(slug, markdown) {
+            error!("Error recording content hash for {}: {}", slug, err);
+        }
+    }
+}
+
+/// WriteFreely does not keep trailing whitespace, so it is ignored.
+fn body_changed(markdown: &str, wf_body: &str) -> bool {
+    markdown.trim_end() != wf_body.trim_end()
+}
+
+fn is_fresh(path: &Path, max_age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age <= max_age)
+}
+
+fn combine_errors(errors: &[String]) -> anyhow::Error {
+    let list: Vec<_> = errors.iter().map(|err| format!("  - {}", err)).collect();
+    anyhow!("{} errors during sync:\n{}", errors.len(), list.join("\n"))
+}
+
+/// Split a --replace-pattern value at the first colon that is not
+/// escaped as `\:`. Other backslashes are kept for the regex.
+fn parse_replace_pattern(arg: &str) -> Result<(Regex, String)> {
+    let mut pattern = String::new();
+    let mut chars = arg.chars();
+
+    loop {
+        match chars.next() {
+            Some('\\') if chars.clone().next() == Some(':') => {
+                pattern.push(':');
+                chars.next();
+            }
+            Some(':') => break,
+            Some(c) => pattern.push(c),
+            None => {
+                return Err(anyhow!(
+                    "Invalid --replace-pattern, expected PAT:REPL: {}",
+                    arg
+                ))
+            }
+        }
+    }
+
+    Ok((Regex::new(&pattern)?, chars.collect()))
+}
+
+fn read_gemlog_url_file(path: &Path) -> Result<Url> {
+    parse_gemlog_url_file(&fs::read_to_string(path)?)
+        .map_err(|err| anyhow!("Invalid Gemlog URL file {}: {}", path.display(), err))
+}
+
+fn parse_gemlog_url_file(contents: &str) -> Result<Url> {
+    let line = contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or(anyhow!("no URL found"))?;
+
+    Ok(Url::parse(line)?)
+}
+
+/// Slugs in the Gemlog that are not yet in the WriteFreely collection.
+fn slugs_not_on_wf(gemfeed: &Gemfeed, wf_slugs: &HashSet<String>) -> Vec<String> {
     let gemfeed_slugs: HashSet<_> = gemfeed.slugs().into_iter().collect();
-    let slugs_to_post: Vec<_> = gemfeed_slugs.difference(&wf_slugs).collect();
+    gemfeed_slugs.difference(wf_slugs).cloned().collect()
+}
 
-    sanitize_gemlogs(gemfeed, config)?;
+/// Sort the slugs by the publish date or feed position of their Gemlog
+/// entries. Undated entries come after the dated ones, in feed order.
+fn order_slugs(gemfeed: &Gemfeed, slugs: &mut [String], order: SyncOrder) {
+    let positions: HashMap<&str, usize> = gemfeed
+        .entries()
+        .enumerate()
+        .map(|(position, entry)| (entry.slug(), position))
+        .collect();
 
-    let gemlogs_to_post = slugs_to_post
-        .into_iter()
-        .flat_map(|slug| gemfeed.find_entry_by_slug(slug));
+    let position = |slug: &String| positions.get(slug.as_str()).copied();
+    let published = |slug: &String| {
+        gemfeed
+            .find_entry_by_slug(slug)
+            .and_then(|e| e.published().copied())
+    };
 
-    let mut count = 0;
-    for entry in gemlogs_to_post {
-        let result = wf.create_post(entry).await;
-        count += 1;
-
-        if let Ok(post) = result {
-            println!(
-                "Created post: {} [title={}]",
-                post.id,
-                post.title.unwrap_or_default()
-            );
-        } else {
-            println!("Error creating post: {} ", result.unwrap_err());
+    // Sorting by feed position first keeps the undated entries, which
+    // compare equal below, in feed order.
+    slugs.sort_by_key(position);
+    match order {
+        SyncOrder::OldestFirst => {
+            slugs.sort_by_key(|slug| (published(slug).is_none(), published(slug)))
         }
+        SyncOrder::NewestFirst => {
+            slugs.sort_by_key(|slug| (published(slug).is_none(), Reverse(published(slug))))
+        }
+        SyncOrder::FeedOrder => slugs.sort_by_key(|slug| published(slug).is_none()),
+    }
+}
+
+/// Whether the entry passes --since-date, --max-age and
+/// --skip-undated. With both a date and a maximum age, the entry must
+/// pass both.
+fn is_published_since(
+    entry: &GemfeedEntry,
+    since_date: Option<DateTime<Utc>>,
+    max_age_days: Option<u32>,
+    skip_undated: bool,
+) -> bool {
+    if entry.published().is_none() {
+        return !skip_undated;
     }
 
-    println!("Post synchronization complete [posts synced={}]", count);
+    let after_since_date = match since_date {
+        Some(date) => !entry.is_published_before(date),
+        None => true,
+    };
+
+    let recent_enough = match (max_age_days, entry.age_days()) {
+        (Some(max_age), Some(age)) => age <= max_age.into(),
+        _ => true,
+    };
+
+    after_since_date && recent_enough
+}
+
+/// Slugs in the WriteFreely collection that are not in the Gemlog.
+fn slugs_to_purge(wf_slugs: &HashSet<String>, gemfeed_slugs: &HashSet<String>) -> Vec<String> {
+    let mut slugs: Vec<_> = wf_slugs.difference(gemfeed_slugs).cloned().collect();
+    slugs.sort();
+    slugs
+}
+
+/// Print the posts a sync would create, returning how many there are.
+fn print_dry_run(gemfeed: &Gemfeed, slugs_to_post: &[String]) -> usize {
+    let entries: Vec<_> = slugs_to_post
+        .iter()
+        .flat_map(|slug| gemfeed.find_entry_by_slug(slug))
+        .collect();
+
+    for entry in &entries {
+        info!(
+            "Would create post: {} [title={}]",
+            entry.slug(),
+            entry.title()
+        );
+    }
+
+    entries.len()
+}
+
+fn print_state(state: Option<&SyncState>) {
+    match state {
+        Some(state) => {
+            info!("Previous sync: {}", state.last_synced.to_rfc3339());
+            info!("  Posts created: {}", state.posts_created.len());
+            info!("  Posts failed: {}", state.posts_failed.len());
+            for slug in &state.posts_failed {
+                info!("    {}", slug);
+            }
+        }
+        None => info!("Previous sync: none"),
+    }
+}
+
+fn print_statistics(gemfeed: &Gemfeed) {
+    let stats = gemfeed.statistics();
+    let fmt_date = |date: Option<DateTime<Utc>>| {
+        date.map(|d| d.to_rfc3339())
+            .unwrap_or_else(|| "none".to_string())
+    };
+
+    info!("Loaded Gemfeed: {}", gemfeed.url());
+    info!("  Entries: {}", stats.entry_count);
+    info!("  Entries without dates: {}", stats.entries_without_dates);
+    info!("  Oldest entry: {}", fmt_date(stats.oldest_entry));
+    info!("  Newest entry: {}", fmt_date(stats.newest_entry));
+
+    if let Some(word_count) = stats.total_estimated_word_count {
+        info!("  Estimated word count: {}", word_count);
+    }
+}
+
+impl From<&SanitizeConfig<'_>> for SanitizationPipeline {
+    fn from(config: &SanitizeConfig) -> Self {
+        let mut pipeline = SanitizationPipeline::new();
+
+        // Frontmatter is at the very top, so it goes first.
+        if config.extract_frontmatter {
+            pipeline = pipeline.apply_frontmatter();
+        }
+
+        for before_marker in config.strip_before_markers {
+            pipeline = pipeline.strip_before(before_marker);
+        }
+
+        for after_marker in config.strip_after_markers {
+            pipeline = pipeline.strip_after(after_marker);
+        }
+
+        if let Some((start, end)) = config.strip_between_markers {
+            pipeline = pipeline.strip_between(start, end);
+        }
+
+        if let Some(marker) = config.links_section_marker {
+            pipeline = pipeline.strip_links_section(marker);
+        }
+
+        for regex in &config.strip_lines_matching {
+            pipeline = pipeline.strip_lines_matching(regex.clone());
+        }
+
+        for (regex, replacement) in &config.replace_patterns {
+            pipeline = pipeline.replace_pattern(regex.clone(), replacement);
+        }
+
+        if config.strip_gemtext_comments {
+            pipeline = pipeline.strip_gemtext_comments();
+        }
+
+        // Before the levels are changed by normalize_headings.
+        if let Some(level) = config.tag_heading_level {
+            pipeline = pipeline.apply_heading_tags(level);
+        }
+
+        if config.normalize_headings {
+            pipeline = pipeline.normalize_headings();
+        }
+
+        if config.linkify_bare_urls {
+            pipeline = pipeline.convert_bare_urls_to_links();
+        }
+
+        // After the steps that remove lines, which can leave blank
+        // lines behind.
+        if config.collapse_blank_lines {
+            pipeline = pipeline.collapse_blank_lines();
+        }
+
+        if let Some(max_words) = config.truncate_to_words {
+            pipeline = pipeline.truncate_to_word_count(max_words, config.truncate_suffix);
+        }
+
+        if let Some(max_chars) = config.summary_max_chars {
+            pipeline = pipeline.then(move |entry| {
+                let summary = entry.summary_with_link(max_chars, &entry.permalink().clone())?;
+                entry.set_gemtext_body(summary);
+                Ok(())
+            });
+        }
+
+        // Last, so that nothing makes the body longer again.
+        if let Some(max_bytes) = config.max_post_bytes {
+            pipeline = pipeline.truncate_post_to_bytes(max_bytes);
+        }
 
-    Ok(())
+        pipeline
+    }
 }
 
 fn sanitize_gemlogs(gemfeed: &mut Gemfeed, config: &SanitizeConfig) -> Result<()> {
-    for entry in gemfeed.entries_mut() {
-        if let Some(ref before_marker) = config.strip_before_marker {
-            sanitization::strip_before(entry, before_marker)?;
+    let pipeline = SanitizationPipeline::from(config);
+    gemfeed
+        .entries_mut()
+        .try_for_each(|entry| pipeline.run(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wf::tests::serve_http;
+    use chrono::{NaiveDate, TimeDelta};
+    use clap::Parser;
+
+    #[test]
+    fn gemlog_url_file_skips_comments_and_blanks() -> Result<()> {
+        let contents = "# My gemlog\n\n  gemini://example.com/gemlog/  \ngemini://other.com/\n";
+        let url = parse_gemlog_url_file(contents)?;
+        assert_eq!(url, Url::parse("gemini://example.com/gemlog/")?);
+        Ok(())
+    }
+
+    #[test]
+    fn combine_errors_lists_all() {
+        let errors = vec![
+            "Loading a: timeout".to_string(),
+            "Creating b: 500".to_string(),
+        ];
+        let err = combine_errors(&errors);
+        assert_eq!(
+            err.to_string(),
+            "2 errors during sync:\n  - Loading a: timeout\n  - Creating b: 500"
+        );
+    }
+
+    #[test]
+    fn dry_run_counts_new_posts_without_fetching() -> Result<()> {
+        let url = Url::parse("gemini://example.com/posts/")?;
+        let entries = ["old", "new1", "new2"]
+            .into_iter()
+            .map(|slug| GemfeedEntry::new(url.join(slug).unwrap(), slug, slug, None))
+            .collect();
+        let gemfeed = Gemfeed::new(&url, "Posts", entries);
+        let wf_slugs = HashSet::from(["old".to_string()]);
+
+        let mut slugs_to_post = slugs_not_on_wf(&gemfeed, &wf_slugs);
+        slugs_to_post.sort();
+        assert_eq!(slugs_to_post, vec!["new1", "new2"]);
+
+        assert_eq!(print_dry_run(&gemfeed, &slugs_to_post), 2);
+        assert!(gemfeed.entries().all(|entry| !entry.has_body_loaded()));
+        Ok(())
+    }
+
+    /// Requests received by the mock server, as `METHOD path body`.
+    type RequestLog = std::sync::Arc<std::sync::Mutex<Vec<String>>>;
+
+    /// Minimal WriteFreely API with one post, "synced", in the
+    /// collection and one draft, "waiting". Creating the post with the
+    /// slug "bad" fails. Each connection gets one answer.
+    fn serve_mock_writefreely() -> Result<(Url, RequestLog)> {
+        let requests = RequestLog::default();
+        let log = requests.clone();
+
+        let url = serve_http(move |request| {
+            log.lock().unwrap().push(format!(
+                "{} {} {}",
+                request.method, request.path, request.body
+            ));
+
+            let path = request.path.as_str();
+            match path {
+                "/api/me" => (
+                    200,
+                    r#"{"code":200,"data":{"username":"alice"}}"#.to_string(),
+                ),
+                "/api/collections/blog/posts?page=1" => (
+                    200,
+                    serde_json::json!({"code": 200, "data": {"posts": [{
+                        "id": "id-synced", "slug": "synced", "title": "",
+                        "body": "", "appearance": "norm", "language": "en",
+                        "rtl": false, "tags": [], "views": 0
+                    }]}})
+                    .to_string(),
+                ),
+                "/api/me/posts" => (
+                    200,
+                    serde_json::json!({"code": 200, "data": [{
+                        "id": "id-waiting", "slug": "waiting", "title": "",
+                        "body": "", "appearance": "norm", "language": "en",
+                        "rtl": false, "tags": [], "views": 0
+                    }]})
+                    .to_string(),
+                ),
+                // Getting a collection post, or updating one by ID.
+                _ if path.starts_with("/api/collections/blog/posts/")
+                    || path.starts_with("/api/posts/id-") =>
+                {
+                    let slug = path.rsplit(['/', '-']).next().unwrap_or_default();
+                    (
+                        200,
+                        serde_json::json!({"code": 200, "data": {
+                            "id": format!("id-{}", slug), "slug": slug,
+                            "title": "", "body": "Old body", "appearance": "norm",
+                            "language": "en", "rtl": false, "tags": [], "views": 0
+                        }})
+                        .to_string(),
+                    )
+                }
+                "/api/posts/disperse" => (200, r#"{"code":200,"data":[]}"#.to_string()),
+                // Creating a post in the collection, or a draft.
+                "/api/collections/blog/posts" | "/api/posts" => {
+                    let req: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                    match req["slug"].as_str().unwrap_or_default() {
+                        "bad" => (400, r#"{"code":400,"error_msg":"Bad post"}"#.to_string()),
+                        slug => (
+                            201,
+                            serde_json::json!({"code": 201, "data": {
+                                "id": format!("id-{}", slug), "slug": slug,
+                                "title": "", "body": "", "appearance": "norm",
+                                "language": "en", "rtl": false, "tags": [], "views": 0
+                            }})
+                            .to_string(),
+                        ),
+                    }
+                }
+                _ => (404, r#"{"code":404,"error_msg":"Not found"}"#.to_string()),
+            }
+        })?;
+
+        Ok((url, requests))
+    }
+
+    #[tokio::test]
+    async fn sync_reports_json() -> Result<()> {
+        use crate::output::tests::SharedBuffer;
+        use crate::output::JsonReporter;
+
+        let gemlog_url = Url::parse("gemini://example.com/posts/")?;
+        let entries = ["good", "bad"]
+            .into_iter()
+            .map(|slug| {
+                let mut entry = GemfeedEntry::new(gemlog_url.join(slug)?, slug, slug, None);
+                entry.set_body(format!("# {}\n\nBody\n", slug));
+                Ok(entry)
+            })
+            .collect::<Result<_>>()?;
+
+        let snapshot = std::env::temp_dir().join(format!(
+            "gemfreely-sync-json-{}.json.gz",
+            std::process::id()
+        ));
+        Gemfeed::new(&gemlog_url, "Posts", entries).save_snapshot(&snapshot)?;
+
+        let (wf_url, _) = serve_mock_writefreely()?;
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            wf_url.as_str(),
+            "--gemlog-url",
+            gemlog_url.as_str(),
+            "--feed-snapshot-cache",
+            &snapshot.to_string_lossy(),
+        ]);
+
+        let buffer = SharedBuffer::default();
+        let reporter = JsonReporter::with_writer(buffer.clone());
+        let result = SyncCommand::try_from(&cli)?.execute(&reporter).await;
+        fs::remove_file(&snapshot)?;
+        result?;
+
+        let output: serde_json::Value = serde_json::from_str(&buffer.contents())?;
+        assert_eq!(output["status"], "error");
+        assert_eq!(output["synced"], serde_json::json!(["good"]));
+        assert_eq!(output["failed"][0]["slug"], "bad");
+        assert!(output["failed"][0]["error"]
+            .as_str()
+            .is_some_and(|err| err.contains("Bad post")));
+        assert_eq!(output["skipped"], serde_json::json!([]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn publish_as_draft_creates_posts_as_drafts() -> Result<()> {
+        let gemlog_url = Url::parse("gemini://example.com/posts/")?;
+        let entry = GemfeedEntry::new(gemlog_url.join("draft")?, "draft", "Draft", None)
+            .with_body("# Draft\n\nBody\n".into());
+
+        let snapshot = std::env::temp_dir().join(format!(
+            "gemfreely-sync-draft-{}.json.gz",
+            std::process::id()
+        ));
+        Gemfeed::new(&gemlog_url, "Posts", vec![entry]).save_snapshot(&snapshot)?;
+
+        let (wf_url, requests) = serve_mock_writefreely()?;
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            wf_url.as_str(),
+            "--gemlog-url",
+            gemlog_url.as_str(),
+            "--feed-snapshot-cache",
+            &snapshot.to_string_lossy(),
+            "--publish-as-draft",
+        ]);
+
+        let result = SyncCommand::try_from(&cli)?
+            .execute(&crate::output::TextReporter)
+            .await;
+        fs::remove_file(&snapshot)?;
+        result?;
+
+        // Never created in the collection, where it would be public.
+        let requests = requests.lock().unwrap();
+        assert!(
+            requests
+                .iter()
+                .any(|req| req.starts_with("POST /api/posts ")),
+            "{:?}",
+            requests
+        );
+        assert!(!requests
+            .iter()
+            .any(|req| req.starts_with("POST /api/collections/blog/posts ")
+                || req.starts_with("POST /api/posts/disperse ")));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drafts_of_earlier_syncs_are_not_created_again() -> Result<()> {
+        let gemlog_url = Url::parse("gemini://example.com/posts/")?;
+        let entry = GemfeedEntry::new(gemlog_url.join("waiting")?, "waiting", "Waiting", None)
+            .with_body("# Waiting\n".into());
+
+        let snapshot = std::env::temp_dir().join(format!(
+            "gemfreely-sync-waiting-{}.json.gz",
+            std::process::id()
+        ));
+        Gemfeed::new(&gemlog_url, "Posts", vec![entry]).save_snapshot(&snapshot)?;
+
+        let (wf_url, requests) = serve_mock_writefreely()?;
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            wf_url.as_str(),
+            "--gemlog-url",
+            gemlog_url.as_str(),
+            "--feed-snapshot-cache",
+            &snapshot.to_string_lossy(),
+        ]);
+
+        let result = SyncCommand::try_from(&cli)?
+            .execute(&crate::output::TextReporter)
+            .await;
+        fs::remove_file(&snapshot)?;
+        result?;
+
+        let requests = requests.lock().unwrap();
+        assert!(
+            !requests.iter().any(|req| req.starts_with("POST ")),
+            "{:?}",
+            requests
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skip_slugs_are_not_created() -> Result<()> {
+        use crate::output::tests::SharedBuffer;
+        use crate::output::JsonReporter;
+
+        let gemlog_url = Url::parse("gemini://example.com/posts/")?;
+        let entries = ["one", "two", "three"]
+            .into_iter()
+            .map(|slug| {
+                let entry = GemfeedEntry::new(gemlog_url.join(slug)?, slug, slug, None);
+                Ok(entry.with_body(format!("# {}\n", slug)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let snapshot = std::env::temp_dir().join(format!(
+            "gemfreely-sync-skip-{}.json.gz",
+            std::process::id()
+        ));
+        Gemfeed::new(&gemlog_url, "Posts", entries).save_snapshot(&snapshot)?;
+
+        let (wf_url, requests) = serve_mock_writefreely()?;
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            wf_url.as_str(),
+            "--gemlog-url",
+            gemlog_url.as_str(),
+            "--feed-snapshot-cache",
+            &snapshot.to_string_lossy(),
+            "--skip-slug",
+            "one",
+            "--skip-slug",
+            "three",
+            // Case-sensitive, so this skips nothing.
+            "--skip-slug",
+            "TWO",
+        ]);
+
+        let buffer = SharedBuffer::default();
+        let reporter = JsonReporter::with_writer(buffer.clone());
+        let result = SyncCommand::try_from(&cli)?.execute(&reporter).await;
+        fs::remove_file(&snapshot)?;
+        result?;
+
+        let requests = requests.lock().unwrap();
+        let created: Vec<_> = requests
+            .iter()
+            .filter(|req| req.starts_with("POST /api/collections/blog/posts "))
+            .collect();
+        assert_eq!(created.len(), 1, "{:?}", requests);
+        assert!(created[0].contains(r#""title":"two""#), "{:?}", created);
+
+        let output: serde_json::Value = serde_json::from_str(&buffer.contents())?;
+        assert_eq!(output["synced"], serde_json::json!(["two"]));
+        assert_eq!(output["skipped"], serde_json::json!(["one", "three"]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bodies_are_only_fetched_for_posts_to_create() -> Result<()> {
+        // Nothing is listening on the port once the listener is dropped.
+        let port = std::net::TcpListener::bind("localhost:0")?
+            .local_addr()?
+            .port();
+        let gemlog_url = Url::parse(&format!("gemini://localhost:{}/posts/", port))?;
+        let entries = vec![
+            GemfeedEntry::new(gemlog_url.join("synced")?, "synced", "Synced", None),
+            GemfeedEntry::new(gemlog_url.join("new")?, "new", "New", None)
+                .with_body("# New\n".into()),
+        ];
+
+        let snapshot = std::env::temp_dir().join(format!(
+            "gemfreely-sync-fetch-{}.json.gz",
+            std::process::id()
+        ));
+        Gemfeed::new(&gemlog_url, "Posts", entries).save_snapshot(&snapshot)?;
+
+        let (wf_url, requests) = serve_mock_writefreely()?;
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "--max-retries",
+            "0",
+            "sync",
+            "--wf-url",
+            wf_url.as_str(),
+            "--gemlog-url",
+            gemlog_url.as_str(),
+            "--feed-snapshot-cache",
+            &snapshot.to_string_lossy(),
+        ]);
+
+        let result = SyncCommand::try_from(&cli)?
+            .execute(&crate::output::TextReporter)
+            .await;
+        fs::remove_file(&snapshot)?;
+        result?;
+
+        let requests = requests.lock().unwrap();
+        let created: Vec<_> = requests
+            .iter()
+            .filter(|req| req.starts_with("POST /api/collections/blog/posts "))
+            .collect();
+        assert_eq!(created.len(), 1, "{:?}", requests);
+        assert!(created[0].contains(r#""title":"New""#), "{:?}", created);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hash_store_decides_which_posts_are_updated() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("gemfreely-sync-hashes-{}.json", std::process::id()));
+        let hash_store = HashStore::new(&path);
+
+        let gemlog_url = Url::parse("gemini://example.com/posts/")?;
+        let entries: Vec<_> = ["same", "changed"]
+            .into_iter()
+            .map(|slug| {
+                GemfeedEntry::new(gemlog_url.join(slug).unwrap(), slug, slug, None)
+                    .with_body(format!("# {}\n\nBody\n", slug))
+            })
+            .collect();
+        hash_store.record("same", &wf::post_body(&entries[0])?)?;
+        hash_store.record("changed", "Synced before the Gemlog post changed")?;
+
+        let (wf_url, requests) = serve_mock_writefreely()?;
+        let creds = WriteFreelyCredentials::AccessToken("token");
+        let wf = WriteFreely::new(&wf_url, "blog", &creds).await?;
+        let gemfeed = Gemfeed::new(&gemlog_url, "Posts", entries);
+        let wf_slugs: HashSet<_> = ["same", "changed"].map(String::from).into();
+
+        let mut report = SyncReport::default();
+        update_changed_posts(&gemfeed, &wf, &wf_slugs, Some(&hash_store), &mut report).await;
+        let hashes = hash_store.load()?;
+        fs::remove_file(&path)?;
+
+        let requests = requests.lock().unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(!requests.iter().any(|req| req.contains("same")));
+        assert!(requests
+            .iter()
+            .any(|req| req.starts_with("POST /api/posts/id-changed ")));
+        assert_eq!(
+            hashes["changed"].content_hash,
+            content_hash(&wf::post_body(
+                gemfeed.find_entry_by_slug("changed").unwrap()
+            )?)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn draft_older_than_only_drafts_old_posts() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            "https://write.example.com",
+            "--draft-older-than",
+            "365",
+        ]);
+        let cmd = SyncCommand::try_from(&cli)?;
+
+        let mut entries = entries_aged(&[10, 400])?;
+        entries.extend(entries_around_2024_03_01()?.pop());
+        let drafts: Vec<_> = entries
+            .iter()
+            .filter(|entry| cmd.is_draft(entry))
+            .map(|entry| entry.slug())
+            .collect();
+
+        assert_eq!(drafts, vec!["400-days"]);
+        Ok(())
+    }
+
+    #[test]
+    fn several_gemlog_urls() -> Result<()> {
+        let args = [
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            "https://write.example.com",
+            "--gemlog-url",
+            "gemini://one.example.com/",
+            "--gemlog-url",
+            "gemini://two.example.com/",
+        ];
+
+        let cli = Cli::parse_from(args);
+        let cmd = SyncCommand::try_from(&cli)?;
+        assert_eq!(
+            cmd.gemlog_urls,
+            ["gemini://one.example.com/", "gemini://two.example.com/"]
+        );
+
+        let cli = Cli::parse_from(args.into_iter().chain(["--purge"]));
+        assert!(SyncCommand::try_from(&cli).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn wf_collection_defaults_to_alias() -> Result<()> {
+        let args = [
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            "https://write.example.com",
+            "--gemlog-url",
+            "gemini://example.com/",
+        ];
+
+        let cli = Cli::parse_from(args);
+        let cmd = SyncCommand::try_from(&cli)?;
+        assert_eq!(cmd.wf_alias, "blog");
+        assert_eq!(cmd.wf_collection, "blog");
+
+        let cli = Cli::parse_from(
+            args.into_iter()
+                .chain(["--wf-collection", "gemlog-archive"]),
+        );
+        let cmd = SyncCommand::try_from(&cli)?;
+        assert_eq!(cmd.wf_alias, "blog");
+        assert_eq!(cmd.wf_collection, "gemlog-archive");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unreachable_gemlogs_are_skipped() -> Result<()> {
+        use crate::output::tests::SharedBuffer;
+        use crate::output::JsonReporter;
+
+        // Nothing is listening on the port once the listener is dropped.
+        let port = std::net::TcpListener::bind("localhost:0")?
+            .local_addr()?
+            .port();
+        let one = format!("gemini://localhost:{}/one/", port);
+        let two = format!("gemini://localhost:{}/two/", port);
+
+        let (wf_url, _) = serve_mock_writefreely()?;
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "--max-retries",
+            "0",
+            "sync",
+            "--wf-url",
+            wf_url.as_str(),
+            "--gemlog-url",
+            &one,
+            "--gemlog-url",
+            &two,
+        ]);
+
+        let buffer = SharedBuffer::default();
+        let reporter = JsonReporter::with_writer(buffer.clone());
+        SyncCommand::try_from(&cli)?.execute(&reporter).await?;
+
+        let output: serde_json::Value = serde_json::from_str(&buffer.contents())?;
+        assert_eq!(output["status"], "error");
+        assert_eq!(output["synced"], serde_json::json!([]));
+        Ok(())
+    }
+
+    #[test]
+    fn body_changed_ignores_trailing_whitespace() {
+        assert!(!body_changed("# Title\n\nText\n", "# Title\n\nText"));
+        assert!(body_changed("# Title\n\nNew text\n", "# Title\n\nText"));
+    }
+
+    #[test]
+    fn purge_deletes_slugs_missing_from_gemlog() {
+        let wf_slugs = HashSet::from(["kept".to_string(), "gone".to_string(), "old".to_string()]);
+        let gemfeed_slugs = HashSet::from(["kept".to_string(), "new".to_string()]);
+        assert_eq!(
+            slugs_to_purge(&wf_slugs, &gemfeed_slugs),
+            vec!["gone", "old"]
+        );
+    }
+
+    #[test]
+    fn purge_is_off_by_default() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--gemlog-url",
+            "gemini://example.com/",
+            "--wf-url",
+            "https://write.example.com",
+        ]);
+
+        assert!(!SyncCommand::try_from(&cli)?.purge);
+        Ok(())
+    }
+
+    #[test]
+    fn purge_rejects_entry_limit() {
+        for limit in ["--newest-first-limit", "--max-feed-entries"] {
+            let cli = Cli::parse_from([
+                "gemfreely",
+                "-t",
+                "token",
+                "-a",
+                "blog",
+                "sync",
+                "--gemlog-url",
+                "gemini://example.com/",
+                "--wf-url",
+                "https://write.example.com",
+                "--purge",
+                limit,
+                "5",
+            ]);
+
+            assert!(SyncCommand::try_from(&cli).is_err(), "{}", limit);
+        }
+    }
+
+    fn noon(month: u32, day: u32) -> Option<DateTime<Utc>> {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .map(|date| date.and_utc())
+    }
+
+    fn entries_around_2024_03_01() -> Result<Vec<GemfeedEntry>> {
+        let url = Url::parse("gemini://example.com/posts/")?;
+        Ok(vec![
+            GemfeedEntry::new(url.clone(), "before", "Before", noon(2, 29)),
+            GemfeedEntry::new(url.clone(), "same-day", "Same day", noon(3, 1)),
+            GemfeedEntry::new(url.clone(), "after", "After", noon(3, 20)),
+            GemfeedEntry::new(url, "undated", "Undated", None),
+        ])
+    }
+
+    #[test]
+    fn since_date_keeps_later_and_undated_entries() -> Result<()> {
+        let since = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc());
+
+        let entries = entries_around_2024_03_01()?;
+        let (kept, dropped): (Vec<_>, Vec<_>) = entries
+            .iter()
+            .partition(|entry| is_published_since(entry, since, None, false));
+
+        let slugs = |entries: Vec<&GemfeedEntry>| -> Vec<String> {
+            entries.iter().map(|e| e.slug().to_owned()).collect()
+        };
+
+        assert_eq!(slugs(kept), vec!["same-day", "after", "undated"]);
+        assert_eq!(slugs(dropped), vec!["before"]);
+        Ok(())
+    }
+
+    #[test]
+    fn skip_undated_drops_entries_without_dates() -> Result<()> {
+        let entries = entries_around_2024_03_01()?;
+        let kept: Vec<_> = entries
+            .iter()
+            .filter(|entry| is_published_since(entry, None, None, true))
+            .map(|entry| entry.slug())
+            .collect();
+
+        assert_eq!(kept, vec!["before", "same-day", "after"]);
+        Ok(())
+    }
+
+    fn days_ago(days: i64) -> DateTime<Utc> {
+        Utc::now() - TimeDelta::try_days(days).expect("days out of range")
+    }
+
+    fn entries_aged(days: &[i64]) -> Result<Vec<GemfeedEntry>> {
+        let url = Url::parse("gemini://example.com/posts/")?;
+        Ok(days
+            .iter()
+            .map(|&age| {
+                let slug = format!("{}-days", age);
+                GemfeedEntry::new(url.clone(), &slug, &slug, Some(days_ago(age)))
+            })
+            .collect())
+    }
+
+    #[test]
+    fn max_age_drops_older_entries() -> Result<()> {
+        let entries = entries_aged(&[10, 100, 400])?;
+        let kept: Vec<_> = entries
+            .iter()
+            .filter(|entry| is_published_since(entry, None, Some(365), false))
+            .map(|entry| entry.slug())
+            .collect();
+
+        assert_eq!(kept, vec!["10-days", "100-days"]);
+        Ok(())
+    }
+
+    #[test]
+    fn max_age_and_since_date_both_apply() -> Result<()> {
+        let mut entries = entries_aged(&[10, 100, 400])?;
+        entries.extend(entries_around_2024_03_01()?.pop());
+        let since = Some(days_ago(50));
+
+        let kept: Vec<_> = entries
+            .iter()
+            .filter(|entry| is_published_since(entry, since, Some(365), false))
+            .map(|entry| entry.slug())
+            .collect();
+
+        assert_eq!(kept, vec!["10-days", "undated"]);
+        Ok(())
+    }
+
+    #[test]
+    fn override_publish_date_only_changes_post_date() -> Result<()> {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--wf-url",
+            "https://write.example.com",
+            "--gemlog-url",
+            "gemini://example.com/",
+            "--override-publish-date",
+            "2025-06-01",
+        ]);
+        let cmd = SyncCommand::try_from(&cli)?;
+
+        let mut entries: Vec<_> = entries_around_2024_03_01()?
+            .into_iter()
+            .filter(|entry| entry.published().is_some())
+            .map(|entry| entry.with_body("Text".into()))
+            .collect();
+        for entry in &mut entries {
+            entry.set_republish_date(cmd.post_date_override());
         }
 
-        if let Some(ref after_marker) = config.strip_after_marker {
-            sanitization::strip_after(entry, after_marker)?;
+        for entry in &entries {
+            let request = writefreely_client::post::PostCreateRequest::try_from(entry)?;
+            let json = serde_json::to_value(request)?;
+            assert_eq!(json["created"], "2025-06-01 12:00:00");
         }
+
+        let since = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc());
+        let kept: Vec<_> = entries
+            .iter()
+            .filter(|entry| is_published_since(entry, since, None, false))
+            .map(|entry| entry.slug())
+            .collect();
+        assert_eq!(kept, vec!["same-day", "after"]);
+        Ok(())
+    }
+
+    #[test]
+    fn order_slugs_oldest_first_then_limit() -> Result<()> {
+        let url = Url::parse("gemini://example.com/posts/")?;
+        let gemfeed = Gemfeed::new(&url, "Posts", entries_around_2024_03_01()?);
+        let mut slugs: Vec<_> = ["after", "undated", "before", "same-day"]
+            .map(String::from)
+            .to_vec();
+
+        order_slugs(&gemfeed, &mut slugs, SyncOrder::OldestFirst);
+        assert_eq!(slugs, vec!["before", "same-day", "after", "undated"]);
+
+        order_slugs(&gemfeed, &mut slugs, SyncOrder::NewestFirst);
+        slugs.truncate(2);
+        assert_eq!(slugs, vec!["after", "same-day"]);
+        Ok(())
+    }
+
+    #[test]
+    fn order_slugs_undated_last_in_every_order() -> Result<()> {
+        let url = Url::parse("gemini://example.com/posts/")?;
+        let entries = vec![
+            GemfeedEntry::new(url.clone(), "middle", "Middle", noon(3, 1)),
+            GemfeedEntry::new(url.clone(), "undated", "Undated", None),
+            GemfeedEntry::new(url.clone(), "oldest", "Oldest", noon(2, 1)),
+            GemfeedEntry::new(url.clone(), "newest", "Newest", noon(3, 20)),
+        ];
+        let gemfeed = Gemfeed::new(&url, "Posts", entries);
+
+        let ordered = |order| {
+            let mut slugs: Vec<_> = ["undated", "newest", "oldest", "middle"]
+                .map(String::from)
+                .to_vec();
+            order_slugs(&gemfeed, &mut slugs, order);
+            slugs
+        };
+
+        assert_eq!(
+            ordered(SyncOrder::OldestFirst),
+            vec!["oldest", "middle", "newest", "undated"]
+        );
+        assert_eq!(
+            ordered(SyncOrder::NewestFirst),
+            vec!["newest", "middle", "oldest", "undated"]
+        );
+        assert_eq!(
+            ordered(SyncOrder::FeedOrder),
+            vec!["middle", "oldest", "newest", "undated"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parse_replace_pattern_with_escaped_colon() -> Result<()> {
+        let (regex, replacement) = parse_replace_pattern(r"gemini\://(\w+):https://$1")?;
+        assert_eq!(regex.as_str(), r"gemini://(\w+)");
+        assert_eq!(replacement, "https://$1");
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn invalid_strip_lines_pattern_is_an_error() {
+        let cli = Cli::parse_from([
+            "gemfreely",
+            "-t",
+            "token",
+            "-a",
+            "blog",
+            "sync",
+            "--gemlog-url",
+            "gemini://example.com/",
+            "--wf-url",
+            "https://write.example.com",
+            "--strip-lines-matching",
+            "(",
+        ]);
+
+        assert!(SyncCommand::try_from(&cli).is_err());
+    }
+
+    #[test]
+    fn parse_replace_pattern_errors() {
+        assert!(parse_replace_pattern("no separator").is_err());
+        assert!(parse_replace_pattern("(:x").is_err());
+    }
+
+    #[test]
+    fn strip_before_markers_in_sequence() -> Result<()> {
+        let gemtext = "Header\n---\nIntro\n===\nContent\n";
+        let markers = ["---".to_string(), "===".to_string()];
+        let config = SanitizeConfig {
+            strip_before_markers: &markers,
+            ..Default::default()
+        };
+
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+        SanitizationPipeline::from(&config).run(&mut entry)?;
+
+        let mut expected = GemfeedEntry::default().with_body(gemtext.into());
+        sanitization::strip_before(&mut expected, "---")?;
+        sanitization::strip_before(&mut expected, "===")?;
+
+        assert_eq!(entry.body()?, expected.body()?);
+        assert_eq!(entry.body()?, "\nContent\n");
+        Ok(())
+    }
+
+    #[test]
+    fn summary_of_plain_text_post_is_converted() -> Result<()> {
+        let config = SanitizeConfig {
+            summary_max_chars: Some(100),
+            ..Default::default()
+        };
+
+        let url = Url::parse("gemini://example.com/notes.txt")?;
+        let mut entry = GemfeedEntry::new(url.clone(), "notes", "Notes", None)
+            .with_body("Plain notes.\n\nMore.\n".into())
+            .with_mime_type("text/plain");
+        SanitizationPipeline::from(&config).run(&mut entry)?;
+
+        assert_eq!(
+            wf::post_body(&entry)?,
+            format!("Plain notes.\n\n[Read the full post on Gemini]({})\n", url)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn heading_tags_are_sent_as_hashtags() -> Result<()> {
+        let config = SanitizeConfig {
+            tag_heading_level: Some(2),
+            ..Default::default()
+        };
+        let pipeline = SanitizationPipeline::from(&config);
+
+        let mut entry = GemfeedEntry::default().with_body("# Title\n## Rust\nText\n".into());
+        pipeline.run(&mut entry)?;
+        assert_eq!(entry.tags(), ["Rust"]);
+        assert!(crate::wf::post_body(&entry)?.ends_with("\n\n#Rust\n"));
+
+        let mut untagged = GemfeedEntry::default().with_body("# Title\nText\n".into());
+        pipeline.run(&mut untagged)?;
+        assert!(untagged.tags().is_empty());
+        assert_eq!(
+            crate::wf::post_body(&untagged)?,
+            untagged.body_as_markdown()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn gemlog_url_file_without_url() {
+        assert!(parse_gemlog_url_file("# only a comment\n\n").is_err());
+    }
 }
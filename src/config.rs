@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::{Cli, Command};
+
+/// Default settings loaded from a TOML file with --config. Every value
+/// is optional, and flags given on the command line take precedence.
+///
+/// ```toml
+/// [writefreely]
+/// url = "https://write.example.com"
+/// alias = "myblog"
+/// token = "00000000-0000-0000-0000-000000000000"
+///
+/// [sync]
+/// gemlog_url = "gemini://example.com/gemlog/"
+/// strip_before_marker = "---"
+/// max_entries = 10
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GemfreelyConfig {
+    #[serde(default)]
+    pub writefreely: WriteFreelyConfig,
+
+    #[serde(default)]
+    pub sync: SyncConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WriteFreelyConfig {
+    pub url: Option<String>,
+    pub alias: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
+    pub gemlog_url: Option<String>,
+    pub strip_before_marker: Option<String>,
+    pub strip_after_marker: Option<String>,
+    pub date_format: Option<String>,
+    pub max_entries: Option<usize>,
+}
+
+impl GemfreelyConfig {
+    pub fn load(path: &Path) -> Result<GemfreelyConfig> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|err| anyhow!("Invalid config file {}: {}", path.display(), err))
+    }
+
+    /// Fill in the values not given on the command line. The sync
+    /// values only apply to the sync command.
+    pub fn apply_to(self, cli: &mut Cli) {
+        let wf = self.writefreely;
+        let sync = self.sync;
+
+        fill(&mut cli.wf_alias, wf.alias);
+        fill(&mut cli.date_format, sync.date_format);
+
+        // Either kind of token given on the command line wins.
+        if cli.oauth_token.is_none() {
+            fill(&mut cli.wf_access_token, wf.token);
+        }
+
+        if let Some(Command::Sync {
+            ref mut wf_url,
+            ref mut gemlog_url,
+            ref opml_file,
+            ref gemlog_url_file,
+            ref mut strip_before_marker,
+            ref mut strip_after_marker,
+            ref mut newest_first_limit,
+            ..
+        }) = cli.command
+        {
+            fill(wf_url, wf.url);
+            fill_list(strip_before_marker, sync.strip_before_marker);
+            fill_list(strip_after_marker, sync.strip_after_marker);
+            fill(newest_first_limit, sync.max_entries);
+
+            // The other Gemlog sources conflict with --gemlog-url.
+            if opml_file.is_none() && gemlog_url_file.is_none() {
+                fill_list(gemlog_url, sync.gemlog_url);
+            }
+        }
+    }
+}
+
+fn fill<T>(cli_value: &mut Option<T>, config_value: Option<T>) {
+    if cli_value.is_none() {
+        *cli_value = config_value;
+    }
+}
+
+fn fill_list<T>(cli_values: &mut Vec<T>, config_value: Option<T>) {
+    if cli_values.is_empty() {
+        cli_values.extend(config_value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    const CONFIG: &str = r#"
+        [writefreely]
+        url = "https://write.example.com"
+        alias = "myblog"
+        token = "secret"
+
+        [sync]
+        gemlog_url = "gemini://example.com/gemlog/"
+        strip_before_marker = "---"
+        strip_after_marker = "=> / Home"
+        date_format = "%Y-%m-%d"
+        max_entries = 10
+    "#;
+
+    #[test]
+    fn parse_all_fields() -> Result<()> {
+        let config: GemfreelyConfig = toml::from_str(CONFIG)?;
+
+        assert_eq!(
+            config.writefreely.url.as_deref(),
+            Some("https://write.example.com")
+        );
+        assert_eq!(config.writefreely.alias.as_deref(), Some("myblog"));
+        assert_eq!(config.writefreely.token.as_deref(), Some("secret"));
+        assert_eq!(
+            config.sync.gemlog_url.as_deref(),
+            Some("gemini://example.com/gemlog/")
+        );
+        assert_eq!(config.sync.strip_before_marker.as_deref(), Some("---"));
+        assert_eq!(config.sync.strip_after_marker.as_deref(), Some("=> / Home"));
+        assert_eq!(config.sync.date_format.as_deref(), Some("%Y-%m-%d"));
+        assert_eq!(config.sync.max_entries, Some(10));
+        Ok(())
+    }
+
+    #[test]
+    fn cli_flag_overrides_config() -> Result<()> {
+        let config: GemfreelyConfig = toml::from_str(CONFIG)?;
+        let mut cli = Cli::parse_from(["gemfreely", "-a", "other", "sync"]);
+        config.apply_to(&mut cli);
+
+        assert_eq!(cli.wf_alias.as_deref(), Some("other"));
+        assert_eq!(cli.wf_access_token.as_deref(), Some("secret"));
+
+        if let Some(Command::Sync {
+            wf_url, gemlog_url, ..
+        }) = cli.command
+        {
+            assert_eq!(wf_url.as_deref(), Some("https://write.example.com"));
+            assert_eq!(gemlog_url, vec!["gemini://example.com/gemlog/"]);
+        } else {
+            panic!("Not a sync command");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn config_gemlog_url_not_used_with_opml_file() -> Result<()> {
+        let config: GemfreelyConfig = toml::from_str(CONFIG)?;
+        let mut cli = Cli::parse_from(["gemfreely", "sync", "--opml-file", "feeds.opml"]);
+        config.apply_to(&mut cli);
+
+        if let Some(Command::Sync { gemlog_url, .. }) = cli.command {
+            assert!(gemlog_url.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let result = toml::from_str::<GemfreelyConfig>("[sync]\ngemlog = \"x\"\n");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use clap::ValueEnum;
+use tracing::Level;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+use crate::output::OutputFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LogFormat {
+    /// Plain messages, the same as gemfreely's normal output.
+    Text,
+
+    /// One JSON object per message, for log aggregation.
+    Json,
+}
+
+impl From<LogLevel> for Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Level::ERROR,
+            LogLevel::Warn => Level::WARN,
+            LogLevel::Info => Level::INFO,
+            LogLevel::Debug => Level::DEBUG,
+            LogLevel::Trace => Level::TRACE,
+        }
+    }
+}
+
+/// Set up the global tracing subscriber. Messages go to stdout,
+/// unless stdout is reserved for JSON results.
+pub(crate) fn init(level: LogLevel, format: LogFormat, output: OutputFormat) {
+    let writer = match output {
+        OutputFormat::Text => BoxMakeWriter::new(std::io::stdout),
+        OutputFormat::Json => BoxMakeWriter::new(std::io::stderr),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(Level::from(level))
+        .with_target(false)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => builder.without_time().with_level(false).init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
@@ -1,21 +1,65 @@
-use crate::commands::sync::SyncCommand;
-use clap::{Parser, Subcommand};
-use commands::{login::LoginCommand, logout::LogoutCommand};
+use crate::commands::sync::{SyncCommand, SyncDirection, SyncOrder};
+use clap::{ArgAction, Parser, Subcommand};
+use commands::{
+    capsule_info::CapsuleInfoCommand,
+    collections::{CollectionsAction, CollectionsCommand},
+    export::{ExportCommand, ExportFormat},
+    generate_gemfeed::GenerateGemfeedCommand,
+    list::{ListCommand, ListFormat},
+    login::LoginCommand,
+    logout::LogoutCommand,
+    move_posts::MovePostsCommand,
+    publish::PublishCommand,
+    rename_post::RenamePostCommand,
+};
 
 use anyhow::Result;
+use chrono::NaiveDate;
+use config::GemfreelyConfig;
+use gemfeed::SlugStrategy;
+use logging::{LogFormat, LogLevel};
+use output::{JsonReporter, OutputFormat, Reporter, TextReporter};
+use std::path::PathBuf;
+use tofu::KnownHosts;
+use tracing::warn;
 
+mod change_detection;
+mod commands;
+mod config;
 mod gemfeed;
+mod gemini;
+mod logging;
+mod output;
+mod persistence;
+mod retry;
 mod sanitization;
+mod tofu;
+mod tracking;
 mod wf;
-mod commands;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// WriteFreely access token. Required for sync and logout.
+    /// TOML file with default settings. Command line flags override
+    /// the values in it.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// WriteFreely access token. Sync and logout require this or
+    /// --oauth-token.
     #[arg(short = 't', long, value_name = "TOKEN")]
     wf_access_token: Option<String>,
 
+    /// File containing the WriteFreely access token, which then stays
+    /// out of shell history. --wf-access-token takes precedence.
+    #[arg(long, value_name = "PATH", conflicts_with = "oauth_token")]
+    wf_token_file: Option<PathBuf>,
+
+    /// WriteFreely OAuth 2.0 token. Used the same way as
+    /// --wf-access-token.
+    #[arg(long, value_name = "TOKEN", conflicts_with = "wf_access_token")]
+    oauth_token: Option<String>,
+
     /// WriteFreely blog name/alias. Usually the same as username.
     #[arg(short = 'a', long, value_name = "ALIAS")]
     wf_alias: Option<String>,
@@ -24,12 +68,120 @@ struct Cli {
     #[arg(long, value_name = "FMT")]
     date_format: Option<String>,
 
+    /// Additional date formats for Gemlog Atom publish dates, tried in
+    /// order after the main one. May be repeated.
+    #[arg(long, value_name = "FMT")]
+    extra_date_format: Vec<String>,
+
+    /// PEM file with a client certificate to send to Gemini capsules
+    /// that require one. Requires --gemini-key.
+    #[arg(long, value_name = "PATH")]
+    gemini_cert: Option<PathBuf>,
+
+    /// PEM file with the private key of --gemini-cert.
+    #[arg(long, value_name = "PATH")]
+    gemini_key: Option<PathBuf>,
+
+    /// Accept the certificates of Gemini capsules not connected to
+    /// before without asking. Changed certificates are still refused.
+    #[arg(long)]
+    trust_new_certs: bool,
+
+    /// Times a failed Gemini request, or a WriteFreely post creation
+    /// that could not connect, is retried, waiting twice as long before
+    /// each retry.
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    max_retries: u32,
+
+    /// Redirects followed for a single Gemini request before giving
+    /// up.
+    #[arg(long, value_name = "N", default_value_t = gemfeed::DEFAULT_MAX_REDIRECTS)]
+    max_redirects: usize,
+
+    /// Print more information while running. May be repeated.
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Most detailed kind of message to print.
+    #[arg(long, value_enum, default_value_t = LogLevel::Info)]
+    log_level: LogLevel,
+
+    /// How to print messages.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// How to print command results.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
 
+// Parsed once at startup, so the size of the Sync variant is fine.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum Command {
+    /// Shows what a Gemini capsule returns for a Gemlog URL.
+    CapsuleInfo {
+        /// Full gemini:// URL of Gemlog (Atom feed or Gemfeed).
+        #[arg(long, value_name = "URL")]
+        gemlog_url: String,
+    },
+
+    /// Lists or creates the collections of the user.
+    Collections {
+        /// Root URL of WriteFreely instance.
+        #[arg(long, value_name = "URL")]
+        wf_url: String,
+
+        #[command(subcommand)]
+        action: CollectionsAction,
+    },
+
+    /// Downloads the posts in the collection as files, one per post.
+    Export {
+        /// Root URL of WriteFreely instance.
+        #[arg(long, value_name = "URL")]
+        wf_url: String,
+
+        /// Directory to write the posts to, as `<slug>.md` (or
+        /// `<slug>.gmi` for Gemtext). Created if missing.
+        #[arg(long, value_name = "DIR")]
+        output_dir: PathBuf,
+
+        /// File format of the posts. Conversion to Gemtext is
+        /// best-effort.
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+
+        /// Replace files that already exist instead of skipping them.
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Writes the posts in the collection as a Gemtext Gemfeed.
+    GenerateGemfeed {
+        /// Root URL of WriteFreely instance.
+        #[arg(long, value_name = "URL")]
+        wf_url: String,
+
+        /// File to write the Gemfeed to. Printed if not given.
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    /// Lists the posts in the collection.
+    List {
+        /// Root URL of WriteFreely instance.
+        #[arg(long, value_name = "URL")]
+        wf_url: String,
+
+        /// How to print the posts.
+        #[arg(long, value_enum, default_value_t = ListFormat::Table)]
+        format: ListFormat,
+    },
+
     /// Logs in to WriteFreely and prints an access token.
     Login {
         /// Root URL of WriteFreely instance.
@@ -52,37 +204,396 @@ enum Command {
         wf_url: String,
     },
 
-    /// Synchronize Gemlog posts from Gemini to WriteFreely.
-    Sync {
-        /// Full gemini:// URL of Gemlog (Atom feed or Gemfeed).
+    /// Moves existing posts into collections.
+    MovePosts {
+        /// Root URL of WriteFreely instance.
         #[arg(long, value_name = "URL")]
-        gemlog_url: String,
+        wf_url: String,
+
+        /// CSV file of `post_id,target_alias` lines.
+        #[arg(long, value_name = "PATH")]
+        csv_file: PathBuf,
+    },
 
+    /// Publishes a draft, such as one created by sync with
+    /// --publish-as-draft, in the collection.
+    Publish {
         /// Root URL of WriteFreely instance.
         #[arg(long, value_name = "URL")]
         wf_url: String,
 
-        /// Optional santization rule: Remove all text BEFORE this
-        /// marker in the Gemlog post.
+        /// Slug of the draft.
         #[arg(long)]
-        strip_before_marker: Option<String>,
+        slug: String,
+    },
+
+    /// Changes the slug of a post in the collection, keeping its ID.
+    RenamePost {
+        /// Root URL of WriteFreely instance.
+        #[arg(long, value_name = "URL")]
+        wf_url: String,
+
+        /// Current slug of the post.
+        #[arg(long, value_name = "OLD")]
+        from_slug: String,
+
+        /// New slug for the post.
+        #[arg(long, value_name = "NEW")]
+        to_slug: String,
+    },
+
+    /// Synchronize Gemlog posts from Gemini to WriteFreely.
+    Sync {
+        /// Full gemini:// URL of Gemlog (Atom feed or Gemfeed). May be
+        /// repeated to sync several Gemlogs into the same collection.
+        /// Required unless --opml-file, --gemlog-url-file or a config
+        /// file gives the Gemlog.
+        #[arg(long, value_name = "URL", action = ArgAction::Append)]
+        gemlog_url: Vec<String>,
+
+        /// File containing the Gemlog URL, used instead of
+        /// --gemlog-url. The first line that is not empty or a #
+        /// comment is used.
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["gemlog_url", "opml_file"])]
+        gemlog_url_file: Option<PathBuf>,
+
+        /// OPML file listing the Gemlogs to sync, used instead of
+        /// --gemlog-url. Categories are ignored.
+        #[arg(long, value_name = "PATH", conflicts_with = "gemlog_url")]
+        opml_file: Option<PathBuf>,
+
+        /// Root URL of WriteFreely instance. Required unless given in
+        /// the config file.
+        #[arg(long, value_name = "URL")]
+        wf_url: Option<String>,
+
+        /// Collection to sync the posts to, if not the one named by
+        /// --wf-alias, e.g. a second blog of the same user.
+        #[arg(long, value_name = "ALIAS")]
+        wf_collection: Option<String>,
+
+        /// Create the collection, titled with its alias, if it does
+        /// not exist yet. Not done on a dry run.
+        #[arg(long)]
+        sync_create_if_missing: bool,
+
+        /// Read `tags:` and `language:` lines at the top of Gemlog
+        /// posts, remove them, and use them for the WriteFreely post.
+        /// Tags are added to the post as hashtags.
+        #[arg(long)]
+        extract_frontmatter: bool,
+
+        /// Use the text of Gemtext headings at this level (1-3) as
+        /// tags for the WriteFreely post, e.g. 2 for `## Rust`.
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=3))]
+        extract_tags_from_heading_level: Option<u8>,
+
+        /// Optional santization rule: Remove all text BEFORE this
+        /// marker in the Gemlog post. May be repeated; the markers are
+        /// applied in order.
+        #[arg(long, action = ArgAction::Append)]
+        strip_before_marker: Vec<String>,
 
         /// Optional santization rule: Remove all text AFTER this
-        /// marker in the Gemlog post.
+        /// marker in the Gemlog post. May be repeated; the markers are
+        /// applied in order.
+        #[arg(long, action = ArgAction::Append)]
+        strip_after_marker: Vec<String>,
+
+        /// Optional santization rule: Remove every block of text from
+        /// this marker to --strip-between-end, both markers included.
+        #[arg(long, value_name = "MARKER", requires = "strip_between_end")]
+        strip_between_start: Option<String>,
+
+        /// End marker of the blocks removed by --strip-between-start.
+        #[arg(long, value_name = "MARKER", requires = "strip_between_start")]
+        strip_between_end: Option<String>,
+
+        /// Optional regex for finding the publish date in Gemfeed
+        /// links, for gemlogs that do not use YYYY-MM-DD at the start
+        /// of the link text. The first capture group, if present, is
+        /// used as the date.
+        #[arg(long, value_name = "PATTERN")]
+        gemfeed_date_regex: Option<String>,
+
+        /// How the slug of each WriteFreely post is chosen.
+        #[arg(long, value_enum, default_value_t = SlugStrategy::FileStem)]
+        slug_strategy: SlugStrategy,
+
+        /// Optional santization rule: Truncate the Gemlog post to at
+        /// most this many words.
+        #[arg(long, value_name = "N")]
+        truncate_to_words: Option<usize>,
+
+        /// Optional santization rule: Remove the trailing "## Links"
+        /// section of the Gemlog post.
+        #[arg(long)]
+        strip_links_section: bool,
+
+        /// Optional santization rule: Like --strip-links-section, with
+        /// a different heading marking the links section.
+        #[arg(long, value_name = "TEXT")]
+        strip_links_section_marker: Option<String>,
+
+        /// Optional santization rule: Remove lines starting with `%`,
+        /// which some authors use as comments.
+        #[arg(long)]
+        strip_gemtext_comments: bool,
+
+        /// Optional santization rule: Turn Gemtext headings deeper
+        /// than ### into ### headings.
+        #[arg(long)]
+        normalize_headings: bool,
+
+        /// Optional santization rule: Turn plain-text http(s) URLs
+        /// into Markdown links.
+        #[arg(long)]
+        linkify_bare_urls: bool,
+
+        /// Optional santization rule: Replace runs of blank lines with
+        /// a single blank line.
+        #[arg(long)]
+        collapse_blank_lines: bool,
+
+        /// Optional santization rule: Remove lines matching this
+        /// regex anywhere. May be repeated.
+        #[arg(long, value_name = "PATTERN")]
+        strip_lines_matching: Vec<String>,
+
+        /// Optional santization rule: Replace matches of a regex, given
+        /// as PAT:REPL. Write `\:` for a colon in the pattern. May be
+        /// repeated.
+        #[arg(long, value_name = "PAT:REPL")]
+        replace_pattern: Vec<String>,
+
+        /// Only sync Gemlog posts whose title matches this regex.
+        #[arg(long, value_name = "PATTERN")]
+        filter_title_regex: Option<String>,
+
+        /// Do not sync Gemlog posts whose title matches this regex. Can
+        /// be combined with --filter-title-regex, in which case
+        /// exclusion takes precedence.
+        #[arg(long, value_name = "PATTERN")]
+        exclude_title_regex: Option<String>,
+
+        /// Post only the first paragraph of each Gemlog post, with a
+        /// link to the full post on Gemini.
+        #[arg(long)]
+        summary_only: bool,
+
+        /// Maximum length of the summary posted with --summary-only.
+        #[arg(long, value_name = "N", default_value_t = 500)]
+        summary_max_chars: usize,
+
+        /// Optional santization rule: Cut the Gemlog post down so that
+        /// the Markdown sent to WriteFreely is at most this many bytes,
+        /// for servers that limit post size.
+        #[arg(long, value_name = "N")]
+        max_post_bytes: Option<usize>,
+
+        /// Text appended to posts shortened by --truncate-to-words.
+        #[arg(long, value_name = "TEXT", default_value = sanitization::DEFAULT_TRUNCATE_SUFFIX)]
+        truncate_suffix: String,
+
+        /// Direction of the sync. Only gemini-to-wf is currently
+        /// supported.
+        #[arg(long, value_enum, default_value_t = SyncDirection::GeminiToWf)]
+        sync_direction: SyncDirection,
+
+        /// Use the current time as the WriteFreely post date instead
+        /// of the Gemlog publish date.
+        #[arg(long)]
+        republish_as_new: bool,
+
+        /// Use noon UTC on this date (YYYY-MM-DD) as the WriteFreely
+        /// post date of every new post, e.g. the date of a migration.
+        /// --since-date and --max-age still go by the Gemlog dates.
+        #[arg(long, value_name = "DATE", conflicts_with = "republish_as_new")]
+        override_publish_date: Option<NaiveDate>,
+
+        /// Language of WriteFreely posts (e.g. en, fr) that have none
+        /// from frontmatter or from the Atom feed's xml:lang.
+        #[arg(long, value_name = "LANG")]
+        post_language: Option<String>,
+
+        /// Number of WriteFreely posts to create at the same time.
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        wf_concurrency: usize,
+
+        /// Number of Gemlog post bodies to fetch from Gemini at the
+        /// same time.
+        #[arg(long, value_name = "N", default_value_t = 4)]
+        concurrency: usize,
+
+        /// Create WriteFreely posts with only a title and metadata.
+        /// Gemlog post bodies are not fetched. Later syncs do not fill
+        /// in the bodies of posts created this way.
+        #[arg(long)]
+        no_body: bool,
+
+        /// Keep going when a Gemlog fails to load or a post fails to
+        /// be created, and fail at the end with a list of all errors.
+        #[arg(long)]
+        collect_errors: bool,
+
+        /// List the posts that would be created without creating them.
+        /// Gemlog posts are not sanitized, and their bodies are not
+        /// fetched.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Also update WriteFreely posts that already exist when the
+        /// Gemlog post body has changed since it was synced.
+        #[arg(long, conflicts_with = "no_body")]
+        update_changed: bool,
+
+        /// After syncing, delete WriteFreely posts that are no longer
+        /// in the Gemlog. Cannot be used with several Gemlogs or with
+        /// a limit on the number of Gemlog posts.
+        #[arg(long, conflicts_with = "opml_file")]
+        purge: bool,
+
+        /// Only create posts published on or after this date
+        /// (YYYY-MM-DD, midnight UTC). Posts without a publish date
+        /// are still created unless --skip-undated is given.
+        #[arg(long, value_name = "DATE")]
+        since_date: Option<NaiveDate>,
+
+        /// Only create posts published at most this many days ago.
+        /// Posts without a publish date are still created unless
+        /// --skip-undated is given. May be combined with --since-date.
+        #[arg(long, value_name = "DAYS")]
+        max_age: Option<u32>,
+
+        /// Never create a post for the Gemlog post with this slug, e.g.
+        /// for Gemini-only content. May be repeated.
+        #[arg(long, value_name = "SLUG", action = ArgAction::Append)]
+        skip_slug: Vec<String>,
+
+        /// Do not create posts that have no publish date.
+        #[arg(long)]
+        skip_undated: bool,
+
+        /// Order in which new posts are created.
+        #[arg(long, value_enum, default_value_t = SyncOrder::NewestFirst)]
+        order: SyncOrder,
+
+        /// Create at most N new posts in this run. Repeated runs with
+        /// --order=oldest-first migrate a large Gemlog in batches.
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Create new posts as drafts of the WriteFreely user, outside
+        /// the collection, to be made public with the publish command.
         #[arg(long)]
-        strip_after_marker: Option<String>,
+        publish_as_draft: bool,
+
+        /// Like --publish-as-draft, but only for posts published more
+        /// than this many days ago, so old posts can be reviewed before
+        /// they appear in the collection.
+        #[arg(long, value_name = "DAYS")]
+        draft_older_than: Option<u32>,
+
+        /// Optional file to record the WriteFreely post ID of each
+        /// created post, as `slug=<slug> id=<id>` lines. Posts already
+        /// recorded here are not created again, even if they have
+        /// since left the collection.
+        #[arg(long, value_name = "PATH")]
+        post_id_tracking_file: Option<PathBuf>,
+
+        /// Optional JSON file recording which posts were created and
+        /// which failed, across runs. Shown at the start of each sync.
+        #[arg(long, value_name = "PATH")]
+        state_file: Option<PathBuf>,
+
+        /// Optional JSON file with a SHA-256 hash of each post body as
+        /// last sent to WriteFreely. With --update-changed, posts are
+        /// then only fetched from WriteFreely if they have no hash yet.
+        #[arg(long, value_name = "PATH")]
+        hash_store: Option<PathBuf>,
+
+        /// Only consider the N most recently published posts in the
+        /// Gemlog.
+        #[arg(long, value_name = "N")]
+        newest_first_limit: Option<usize>,
+
+        /// Only parse the first N entries of the Gemlog feed, in the
+        /// order the feed lists them. Cheaper than --newest-first-limit
+        /// for large feeds that list the newest posts first.
+        #[arg(long, value_name = "N", conflicts_with = "newest_first_limit")]
+        max_feed_entries: Option<usize>,
+
+        /// Cache the Gemlog and its post bodies in this file, and use
+        /// the cached copy instead of fetching from Gemini while it is
+        /// fresh.
+        #[arg(long, value_name = "PATH", conflicts_with = "opml_file")]
+        feed_snapshot_cache: Option<PathBuf>,
+
+        /// How long the --feed-snapshot-cache stays fresh.
+        #[arg(long, value_name = "N", default_value_t = 60)]
+        cache_max_age_minutes: u64,
+
+        /// Optional timeout in seconds for each Gemini request made
+        /// while loading the Gemlog and its posts.
+        #[arg(long, value_name = "SECS")]
+        gemini_timeout: Option<u64>,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    logging::init(cli.log_level, cli.log_format, cli.output);
+
+    let reporter: Box<dyn Reporter> = match cli.output {
+        OutputFormat::Text => Box::new(TextReporter),
+        OutputFormat::Json => Box::new(JsonReporter::new()),
+    };
+    let reporter = reporter.as_ref();
+
+    if let Some(path) = KnownHosts::default_path() {
+        tofu::init(KnownHosts::load(&path, cli.trust_new_certs)?);
+    }
+
+    // Before the config file, whose token the file overrides.
+    if let Some(path) = cli.wf_token_file.clone() {
+        match cli.wf_access_token {
+            Some(_) => warn!(
+                "--wf-access-token given, ignoring --wf-token-file. \
+                 Tokens on the command line end up in shell history; \
+                 prefer --wf-token-file"
+            ),
+            None => cli.wf_access_token = Some(commands::read_token_file(&path)?),
+        }
+    }
+
+    if let Some(path) = cli.config.clone() {
+        GemfreelyConfig::load(&path)?.apply_to(&mut cli);
+    }
 
     if let Some(ref cmd) = cli.command {
         match cmd {
-            Command::Login { .. } => LoginCommand::try_from(&cli)?.execute().await,
-            Command::Logout { .. } => LogoutCommand::try_from(&cli)?.execute().await,
-            Command::Sync { .. } => SyncCommand::try_from(&cli)?.execute().await,
+            Command::CapsuleInfo { .. } => {
+                CapsuleInfoCommand::try_from(&cli)?.execute(reporter).await
+            }
+            Command::Collections { .. } => {
+                CollectionsCommand::try_from(&cli)?.execute(reporter).await
+            }
+            Command::Export { .. } => ExportCommand::try_from(&cli)?.execute(reporter).await,
+            Command::GenerateGemfeed { .. } => {
+                GenerateGemfeedCommand::try_from(&cli)?
+                    .execute(reporter)
+                    .await
+            }
+            Command::List { .. } => ListCommand::try_from(&cli)?.execute(reporter).await,
+            Command::Login { .. } => LoginCommand::try_from(&cli)?.execute(reporter).await,
+            Command::Logout { .. } => LogoutCommand::try_from(&cli)?.execute(reporter).await,
+            Command::MovePosts { .. } => MovePostsCommand::try_from(&cli)?.execute(reporter).await,
+            Command::Publish { .. } => PublishCommand::try_from(&cli)?.execute(reporter).await,
+            Command::RenamePost { .. } => {
+                RenamePostCommand::try_from(&cli)?.execute(reporter).await
+            }
+            Command::Sync { .. } => SyncCommand::try_from(&cli)?.execute(reporter).await,
         }
     } else {
         Ok(())
@@ -0,0 +1,229 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use writefreely_client::post::Post;
+
+use crate::commands::collections::{render_collections, CollectionsFormat};
+use crate::commands::list::{render_posts, ListFormat};
+use crate::wf::CollectionInfo;
+
+/// How commands print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Human-readable output.
+    Text,
+
+    /// One JSON document per command, for scripts. Log messages go to
+    /// stderr instead of stdout.
+    Json,
+}
+
+/// What a sync did to the posts it considered.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct SyncReport {
+    /// Slugs of the posts created.
+    pub synced: Vec<String>,
+
+    pub failed: Vec<FailedPost>,
+
+    /// Slugs of new posts that were not created, because of the
+    /// tracking file, --since-date, --max-age, --skip-undated,
+    /// --skip-slug, --limit or a dry run.
+    pub skipped: Vec<String>,
+
+    /// Every error, including those not about a single post.
+    #[serde(skip)]
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FailedPost {
+    pub slug: String,
+    pub error: String,
+}
+
+impl SyncReport {
+    /// Record an error about one post. The action says what was being
+    /// done, e.g. "Creating".
+    pub fn fail<E: Display>(&mut self, slug: &str, action: &str, err: E) {
+        self.errors.push(format!("{} {}: {}", action, slug, err));
+        self.failed.push(FailedPost {
+            slug: slug.to_owned(),
+            error: err.to_string(),
+        });
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self.errors.is_empty() {
+            true => "ok",
+            false => "error",
+        }
+    }
+}
+
+/// Prints the results of commands. Progress messages go through
+/// tracing instead, so scripts only have to read the results.
+pub(crate) trait Reporter {
+    fn sync_report(&self, report: &SyncReport) -> Result<()>;
+
+    fn token(&self, token: &str) -> Result<()>;
+
+    fn posts(&self, posts: &[Post], format: ListFormat) -> Result<()>;
+
+    fn collections(&self, collections: &[CollectionInfo], format: CollectionsFormat) -> Result<()>;
+
+    /// The outcome of a command with nothing more specific to say.
+    fn message(&self, message: &str) -> Result<()>;
+}
+
+pub(crate) struct TextReporter;
+
+impl Reporter for TextReporter {
+    fn sync_report(&self, _report: &SyncReport) -> Result<()> {
+        // The sync has already logged each post as it went.
+        Ok(())
+    }
+
+    fn token(&self, token: &str) -> Result<()> {
+        println!("{}", token);
+        Ok(())
+    }
+
+    fn posts(&self, posts: &[Post], format: ListFormat) -> Result<()> {
+        match posts.is_empty() {
+            true => println!("No posts found."),
+            false => print!("{}", render_posts(posts, format)?),
+        }
+        Ok(())
+    }
+
+    fn collections(&self, collections: &[CollectionInfo], format: CollectionsFormat) -> Result<()> {
+        match collections.is_empty() {
+            true => println!("No collections found."),
+            false => print!("{}", render_collections(collections, format)?),
+        }
+        Ok(())
+    }
+
+    fn message(&self, message: &str) -> Result<()> {
+        println!("{}", message);
+        Ok(())
+    }
+}
+
+pub(crate) struct JsonReporter {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonReporter {
+    pub fn new() -> JsonReporter {
+        JsonReporter::with_writer(io::stdout())
+    }
+
+    pub fn with_writer<W: Write + Send + 'static>(writer: W) -> JsonReporter {
+        JsonReporter {
+            out: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    fn emit<T: Serialize>(&self, value: &T) -> Result<()> {
+        let mut out = self.out.lock().expect("JSON output lock poisoned");
+        serde_json::to_writer(&mut *out, value)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn sync_report(&self, report: &SyncReport) -> Result<()> {
+        let mut value = serde_json::to_value(report)?;
+        value["status"] = report.status().into();
+        self.emit(&value)
+    }
+
+    fn token(&self, token: &str) -> Result<()> {
+        self.emit(&json!({ "token": token }))
+    }
+
+    /// Always the full post objects, whatever --format says.
+    fn posts(&self, posts: &[Post], _format: ListFormat) -> Result<()> {
+        self.emit(&posts)
+    }
+
+    /// Always JSON, whatever --format says.
+    fn collections(
+        &self,
+        collections: &[CollectionInfo],
+        _format: CollectionsFormat,
+    ) -> Result<()> {
+        self.emit(&collections)
+    }
+
+    fn message(&self, message: &str) -> Result<()> {
+        self.emit(&json!({ "status": "ok", "message": message }))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    /// Writer whose contents can still be read after it has been
+    /// given to a JsonReporter.
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        pub fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sync_report_as_json() -> Result<()> {
+        let buffer = SharedBuffer::default();
+        let reporter = JsonReporter::with_writer(buffer.clone());
+
+        let mut report = SyncReport::default();
+        report.synced.push("post1".to_string());
+        report.fail("post2", "Creating", "server error");
+        reporter.sync_report(&report)?;
+
+        let value: Value = serde_json::from_str(&buffer.contents())?;
+        assert_eq!(value["status"], "error");
+        assert_eq!(value["synced"], json!(["post1"]));
+        assert_eq!(
+            value["failed"],
+            json!([{ "slug": "post2", "error": "server error" }])
+        );
+        assert_eq!(value["skipped"], json!([]));
+        assert!(value.get("errors").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn token_as_json() -> Result<()> {
+        let buffer = SharedBuffer::default();
+        JsonReporter::with_writer(buffer.clone()).token("abc")?;
+
+        let value: Value = serde_json::from_str(&buffer.contents())?;
+        assert_eq!(value, json!({ "token": "abc" }));
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Human-readable record of what syncs have done, kept across runs.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub last_synced: DateTime<Utc>,
+
+    /// Slugs of all posts created so far.
+    pub posts_created: Vec<String>,
+
+    /// Slugs of posts that failed to be created and have not been
+    /// created successfully since.
+    pub posts_failed: Vec<String>,
+}
+
+impl SyncState {
+    pub fn record_created(&mut self, slug: &str) {
+        self.posts_failed.retain(|failed| failed != slug);
+        if !self.posts_created.iter().any(|created| created == slug) {
+            self.posts_created.push(slug.to_owned());
+        }
+    }
+
+    pub fn record_failed(&mut self, slug: &str) {
+        if !self.posts_failed.iter().any(|failed| failed == slug) {
+            self.posts_failed.push(slug.to_owned());
+        }
+    }
+}
+
+/// JSON file holding the SyncState. Every change is written to a
+/// temporary file first and then renamed over the state file, so an
+/// interrupted sync never leaves partial JSON behind.
+pub struct SyncStateFile {
+    path: PathBuf,
+}
+
+impl SyncStateFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> SyncStateFile {
+        SyncStateFile {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// The stored state, or None if there is no state file yet.
+    pub fn load(&self) -> Result<Option<SyncState>> {
+        match fs::read_to_string(&self.path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, state: &SyncState) -> Result<()> {
+        write_atomically(&self.path, &serde_json::to_string_pretty(state)?)
+    }
+
+    /// Load the state, change it, and save it with the current time
+    /// as the last sync time.
+    pub fn update<F>(&self, change: F) -> Result<()>
+    where
+        F: FnOnce(&mut SyncState),
+    {
+        let mut state = self.load()?.unwrap_or_default();
+        change(&mut state);
+        state.last_synced = Utc::now();
+        self.save(&state)
+    }
+}
+
+/// Write the file through a temporary file next to it, so that an
+/// interrupted write never leaves a half-written file behind.
+pub fn write_atomically<C: AsRef<[u8]>>(path: &Path, contents: C) -> Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gemfreely-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn two_successes_and_one_failure() -> Result<()> {
+        let path = temp_path("state-two-one.json");
+        let state_file = SyncStateFile::new(&path);
+
+        state_file.update(|state| state.record_created("post1"))?;
+        state_file.update(|state| state.record_failed("post2"))?;
+        state_file.update(|state| state.record_created("post3"))?;
+
+        let state = state_file.load()?.expect("state file written");
+        assert_eq!(state.posts_created, vec!["post1", "post3"]);
+        assert_eq!(state.posts_failed, vec!["post2"]);
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn later_success_clears_failure() {
+        let mut state = SyncState::default();
+        state.record_failed("post1");
+        state.record_created("post1");
+
+        assert_eq!(state.posts_created, vec!["post1"]);
+        assert!(state.posts_failed.is_empty());
+    }
+
+    #[test]
+    fn save_replaces_file_atomically() -> Result<()> {
+        let path = temp_path("state-atomic.json");
+        let temp = path.with_file_name(format!(
+            "{}.tmp",
+            path.file_name().unwrap().to_string_lossy()
+        ));
+
+        // A partial write left over from an interrupted run.
+        fs::write(&temp, "{\"last_synced\": ")?;
+        let state_file = SyncStateFile::new(&path);
+        assert_eq!(state_file.load()?, None);
+
+        state_file.update(|state| state.record_created("post1"))?;
+        assert!(!temp.exists());
+        assert!(serde_json::from_str::<SyncState>(&fs::read_to_string(&path)?).is_ok());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn missing_state_file() -> Result<()> {
+        let state_file = SyncStateFile::new(temp_path("state-missing.json"));
+        assert_eq!(state_file.load()?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use anyhow::{Error, Result};
+use std::future::Future;
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+/// Seconds to wait before the first retry. Each later retry waits
+/// twice as long as the one before.
+pub const BASE_DELAY_SECS: u64 = 1;
+
+fn backoff_delay(base_delay_secs: u64, retry: u32) -> Duration {
+    Duration::from_secs(base_delay_secs.saturating_mul(2u64.saturating_pow(retry)))
+}
+
+/// Run `f` until it succeeds, retrying up to `max_retries` times with
+/// exponential backoff. The last error is returned if every attempt
+/// fails.
+#[allow(dead_code)]
+pub async fn with_retry<F, Fut, T>(max_retries: u32, base_delay_secs: u64, f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    with_retry_when(max_retries, base_delay_secs, |_| true, f).await
+}
+
+/// Like with_retry(), but only errors for which `is_transient`
+/// returns true are retried. Others are returned straight away.
+pub async fn with_retry_when<F, Fut, T, P>(
+    max_retries: u32,
+    base_delay_secs: u64,
+    is_transient: P,
+    mut f: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    P: Fn(&Error) -> bool,
+{
+    let mut retry = 0;
+    loop {
+        match f().await {
+            Err(err) if retry < max_retries && is_transient(&err) => {
+                let delay = backoff_delay(base_delay_secs, retry);
+                warn!("Retrying in {}s after error: {}", delay.as_secs(), err);
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// with_retry() for blocking code, such as Gemini requests.
+#[allow(dead_code)]
+pub fn with_retry_blocking<F, T>(max_retries: u32, base_delay_secs: u64, f: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    with_retry_blocking_when(max_retries, base_delay_secs, |_| true, f)
+}
+
+/// with_retry_when() for blocking code.
+pub fn with_retry_blocking_when<F, T, P>(
+    max_retries: u32,
+    base_delay_secs: u64,
+    is_transient: P,
+    mut f: F,
+) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+    P: Fn(&Error) -> bool,
+{
+    let mut retry = 0;
+    loop {
+        match f() {
+            Err(err) if retry < max_retries && is_transient(&err) => {
+                let delay = backoff_delay(base_delay_secs, retry);
+                warn!("Retrying in {}s after error: {}", delay.as_secs(), err);
+                thread::sleep(delay);
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn succeeds_after_two_retries() -> Result<()> {
+        let calls = Cell::new(0);
+        let result = with_retry(3, 0, || {
+            calls.set(calls.get() + 1);
+            let call = calls.get();
+            async move {
+                match call {
+                    1 | 2 => Err(anyhow!("failure {}", call)),
+                    _ => Ok("done"),
+                }
+            }
+        })
+        .await?;
+
+        assert_eq!(result, "done");
+        assert_eq!(calls.get(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let calls = Cell::new(0);
+        let result: Result<()> = with_retry(2, 0, || {
+            calls.set(calls.get() + 1);
+            async { Err(anyhow!("always fails")) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let calls = Cell::new(0);
+        let result: Result<()> = with_retry_when(
+            3,
+            0,
+            |err| !err.to_string().contains("permanent"),
+            || {
+                calls.set(calls.get() + 1);
+                async { Err(anyhow!("permanent failure")) }
+            },
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn blocking_succeeds_after_two_retries() -> Result<()> {
+        let mut calls = 0;
+        let result = with_retry_blocking(3, 0, || {
+            calls += 1;
+            match calls {
+                1 | 2 => Err(anyhow!("failure {}", calls)),
+                _ => Ok(calls),
+            }
+        })?;
+
+        assert_eq!(result, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn backoff_doubles() {
+        assert_eq!(backoff_delay(1, 0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(1, 1), Duration::from_secs(2));
+        assert_eq!(backoff_delay(1, 2), Duration::from_secs(4));
+    }
+}
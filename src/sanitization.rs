@@ -1,25 +1,939 @@
-use crate::gemfeed::GemfeedEntry;
-use anyhow::Result;
+use crate::gemfeed::{self, GemfeedEntry};
+use crate::wf;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+static WORD_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"\S+"#).unwrap());
+
+static FRONTMATTER_LINE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^([a-z][a-z0-9_-]*):\s*(.*?)\s*$"#).unwrap());
+
+/// Markdown links and autolinks are matched first so that the URLs
+/// inside them are not linked a second time.
+static BARE_URL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\[[^\]]*\]\([^)]*\)|<https?://[^>]*>|(https?://\S+)"#).unwrap());
+
+/// Appended to the body of posts cut down by truncate_to_word_count.
+pub const DEFAULT_TRUNCATE_SUFFIX: &str = "\n\n*[truncated]*";
+
+/// Appended to the body of posts cut down by truncate_to_bytes.
+pub const BYTE_TRUNCATION_MARKER: &str = "…";
+
+/// Heading that starts the trailing list of links in many Gemlog posts.
+pub const DEFAULT_LINKS_SECTION_MARKER: &str = "## Links";
 
 pub fn strip_before(entry: &mut GemfeedEntry, marker: &str) -> Result<()> {
     let body = entry.body_mut()?;
     let sanitized_body = match body.find(marker) {
         Some(index) => body.split_at(index + marker.len()).1,
-        _ => &body,
+        _ => body,
     };
 
     *body = sanitized_body.to_owned();
     Ok(())
 }
 
-
 pub fn strip_after(entry: &mut GemfeedEntry, marker: &str) -> Result<()> {
     let body = entry.body_mut()?;
     let sanitized_body = match body.rfind(marker) {
         Some(index) => body.split_at(index).0,
-        _ => &body,
+        _ => body,
     };
 
     *body = sanitized_body.to_owned();
     Ok(())
 }
+
+/// Remove every block that starts with the start marker and ends with
+/// the first end marker after it, both markers included. A start
+/// marker without an end marker after it is an error.
+pub fn strip_between(entry: &mut GemfeedEntry, start: &str, end: &str) -> Result<()> {
+    let body = entry.body_mut()?;
+    let mut stripped = String::with_capacity(body.len());
+    let mut rest_start = 0;
+
+    while let Some(found) = body[rest_start..].find(start) {
+        let block_start = rest_start + found;
+        let block_end = body[block_start + start.len()..]
+            .find(end)
+            .map(|index| block_start + start.len() + index + end.len())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Marker {:?} at byte {} is not followed by {:?}",
+                    start,
+                    block_start,
+                    end
+                )
+            })?;
+
+        stripped.push_str(&body[rest_start..block_start]);
+        rest_start = block_end;
+    }
+
+    stripped.push_str(&body[rest_start..]);
+    *body = stripped;
+    Ok(())
+}
+
+/// Remove the trailing links section: everything from the last
+/// occurrence of the section marker to the end of the post. Inline
+/// links already carry the same URLs once converted to Markdown.
+pub fn strip_links_section(entry: &mut GemfeedEntry, section_marker: &str) -> Result<()> {
+    if !entry.body_contains(section_marker)? {
+        return Ok(());
+    }
+
+    strip_after(entry, section_marker)?;
+
+    let body = entry.body_mut()?;
+    body.truncate(body.trim_end().len());
+    body.push('\n');
+    Ok(())
+}
+
+/// Truncate the body at the last word boundary so that it contains at
+/// most max_words words, then append the suffix. Bodies that are
+/// already short enough are left alone. The body is still Gemtext, so
+/// like truncate_to_bytes(), a cut inside a preformatted block is
+/// moved back to before its opening fence. Text lines pass through
+/// conversion unchanged, so a Markdown suffix stays Markdown.
+pub fn truncate_to_word_count(
+    entry: &mut GemfeedEntry,
+    max_words: usize,
+    suffix: &str,
+) -> Result<()> {
+    let body = entry.body_mut()?;
+    let words: Vec<_> = WORD_REGEX.find_iter(body).collect();
+
+    if words.len() > max_words {
+        let mut cut = match max_words {
+            0 => 0,
+            _ => words[max_words - 1].end(),
+        };
+
+        if let Some(fence) = open_fence_before(body, cut) {
+            cut = fence;
+        }
+
+        body.truncate(cut);
+        body.push_str(suffix);
+    }
+
+    Ok(())
+}
+
+/// Truncate the body so that it is at most max_bytes long, ellipsis
+/// included, without splitting a character. A cut that would fall
+/// inside a preformatted block is moved back to before its opening
+/// fence, so no block is left unclosed.
+pub fn truncate_to_bytes(entry: &mut GemfeedEntry, max_bytes: usize) -> Result<()> {
+    let body = entry.body_mut()?;
+    if body.len() <= max_bytes {
+        return Ok(());
+    }
+
+    let (mut cut, marker) = match max_bytes.checked_sub(BYTE_TRUNCATION_MARKER.len()) {
+        Some(cut) => (cut, BYTE_TRUNCATION_MARKER),
+        None => (max_bytes, ""),
+    };
+
+    while !body.is_char_boundary(cut) {
+        cut -= 1;
+    }
+
+    if let Some(fence) = open_fence_before(body, cut) {
+        cut = fence;
+    }
+
+    body.truncate(cut);
+    body.push_str(marker);
+    Ok(())
+}
+
+/// Truncate the body like truncate_to_bytes() until the Markdown sent
+/// to WriteFreely, hashtags included, is at most max_bytes long.
+/// Conversion and hashtags can make the Markdown longer than the
+/// Gemtext, so the Gemtext is cut further until the Markdown fits.
+pub fn truncate_post_to_bytes(entry: &mut GemfeedEntry, max_bytes: usize) -> Result<()> {
+    loop {
+        let excess = wf::post_body(entry)?.len().saturating_sub(max_bytes);
+        let body_len = entry.body()?.len();
+        if excess == 0 || body_len == 0 {
+            return Ok(());
+        }
+
+        truncate_to_bytes(entry, body_len.saturating_sub(excess))?;
+    }
+}
+
+/// Start of the opening fence of the preformatted block that is still
+/// open at the given byte offset, if any. A closing fence only closes
+/// the block if the whole fence comes before the offset.
+fn open_fence_before(body: &str, offset: usize) -> Option<usize> {
+    let mut open_fence = None;
+    let mut line_start = 0;
+
+    for line in body.split_inclusive('\n') {
+        if line_start >= offset {
+            break;
+        }
+
+        if line.starts_with("```") {
+            open_fence = match open_fence {
+                None => Some(line_start),
+                Some(_) if line_start + 3 <= offset => None,
+                Some(fence) => Some(fence),
+            };
+        }
+
+        line_start += line.len();
+    }
+
+    open_fence
+}
+
+/// Remove `%` comment lines (an informal Gemtext convention) from the
+/// body. Only lines starting with `%` are removed, and lines inside
+/// preformatted blocks are left alone.
+pub fn strip_gemtext_comments(entry: &mut GemfeedEntry) -> Result<()> {
+    let body = entry.body_mut()?;
+    let mut preformatted = false;
+    let mut stripped = String::with_capacity(body.len());
+
+    for line in body.split_inclusive('\n') {
+        if line.starts_with("```") {
+            preformatted = !preformatted;
+        }
+
+        if preformatted || !line.starts_with('%') {
+            stripped.push_str(line);
+        }
+    }
+
+    *body = stripped;
+    Ok(())
+}
+
+/// Wrap plain-text `http(s)://` URLs as `[url](url)` so they are
+/// clickable after conversion to Markdown. Link lines, preformatted
+/// blocks and URLs already in Markdown link markup are left alone. A
+/// text/plain body is posted as Markdown as it is, so it has no link
+/// lines and the links end up in the Markdown directly.
+pub fn convert_bare_urls_to_links(entry: &mut GemfeedEntry) -> Result<()> {
+    let link_lines = !entry.is_plain_text();
+    let body = entry.body_mut()?;
+    let mut preformatted = false;
+    let mut linked = String::with_capacity(body.len());
+
+    for line in body.split_inclusive('\n') {
+        if line.starts_with("```") {
+            preformatted = !preformatted;
+        }
+
+        if preformatted || (link_lines && line.starts_with("=>")) {
+            linked.push_str(line);
+            continue;
+        }
+
+        let line = BARE_URL_REGEX.replace_all(line, |caps: &regex::Captures| match caps.get(1) {
+            Some(url) => {
+                let url = url.as_str();
+                let link = url.trim_end_matches(['.', ',', ';', ':', '!', '?']);
+                format!("[{}]({}){}", link, link, &url[link.len()..])
+            }
+            None => caps[0].to_owned(),
+        });
+
+        linked.push_str(&line);
+    }
+
+    *body = linked;
+    Ok(())
+}
+
+/// Remove the leading block of `key: value` lines (and the blank line
+/// after it) from the body and return them. Keys are lowercase. The
+/// block ends at the first other line, so a body that does not start
+/// with such a line is left alone.
+pub fn extract_frontmatter(entry: &mut GemfeedEntry) -> Result<HashMap<String, String>> {
+    let body = entry.body_mut()?;
+    let mut frontmatter = HashMap::new();
+    let mut end = 0;
+
+    for line in body.split_inclusive('\n') {
+        match FRONTMATTER_LINE_REGEX.captures(line.trim_end_matches('\n')) {
+            Some(caps) => frontmatter.insert(caps[1].to_owned(), caps[2].to_owned()),
+            None => break,
+        };
+        end += line.len();
+    }
+
+    if !frontmatter.is_empty() {
+        let rest = &body[end..];
+        let rest = rest.strip_prefix('\n').unwrap_or(rest);
+        *body = rest.to_owned();
+    }
+
+    Ok(frontmatter)
+}
+
+/// Move the `tags` (comma-separated) and `language` frontmatter keys
+/// to the entry, so they are sent to WriteFreely.
+pub fn apply_frontmatter(entry: &mut GemfeedEntry) -> Result<()> {
+    let frontmatter = extract_frontmatter(entry)?;
+
+    if let Some(tags) = frontmatter.get("tags") {
+        let tags = tags
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_owned)
+            .collect();
+        entry.set_tags(tags);
+    }
+
+    if let Some(language) = frontmatter.get("language") {
+        entry.set_language(Some(language.clone()));
+    }
+
+    Ok(())
+}
+
+/// Add the text of every heading at the given level to the tags of
+/// the entry. Tags the entry already has, e.g. from frontmatter, are
+/// kept and not repeated.
+pub fn apply_heading_tags(entry: &mut GemfeedEntry, level: usize) -> Result<()> {
+    let mut tags = entry.tags().to_vec();
+    for tag in gemfeed::extract_tags_from_headings(&entry.body_as_ast()?, level) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    entry.set_tags(tags);
+    Ok(())
+}
+
+/// Remove every line in which the regex matches anywhere.
+pub fn strip_lines_matching(entry: &mut GemfeedEntry, regex: &Regex) -> Result<()> {
+    let body = entry.body_mut()?;
+    *body = body
+        .split_inclusive('\n')
+        .filter(|line| !regex.is_match(line.trim_end_matches('\n')))
+        .collect();
+    Ok(())
+}
+
+/// Replace every match of the regex in the body. The replacement may
+/// refer to capture groups, e.g. `$1`.
+pub fn replace_pattern(entry: &mut GemfeedEntry, regex: &Regex, replacement: &str) -> Result<()> {
+    let body = entry.body_mut()?;
+    *body = regex.replace_all(body, replacement).into_owned();
+    Ok(())
+}
+
+/// Remove trailing whitespace from every line and collapse runs of
+/// blank lines into a single blank line.
+pub fn normalize_whitespace(entry: &mut GemfeedEntry) -> Result<()> {
+    let body = entry.body_mut()?;
+    let mut normalized = String::with_capacity(body.len());
+    let mut previous_blank = false;
+
+    for line in body.lines().map(str::trim_end) {
+        if line.is_empty() && previous_blank {
+            continue;
+        }
+
+        previous_blank = line.is_empty();
+        normalized.push_str(line);
+        normalized.push('\n');
+    }
+
+    *body = normalized;
+    Ok(())
+}
+
+/// Replace each run of blank lines (including lines of only
+/// whitespace) with a single empty line. Unlike
+/// normalize_whitespace(), other lines are kept as they are, and so
+/// are lines inside preformatted blocks.
+pub fn collapse_blank_lines(entry: &mut GemfeedEntry) -> Result<()> {
+    let body = entry.body_mut()?;
+    let mut preformatted = false;
+    let mut previous_blank = false;
+    let mut collapsed = String::with_capacity(body.len());
+
+    for line in body.split_inclusive('\n') {
+        if line.starts_with("```") {
+            preformatted = !preformatted;
+        }
+
+        let blank = !preformatted && line.trim().is_empty();
+        match blank {
+            true if previous_blank => (),
+            true => collapsed.push('\n'),
+            false => collapsed.push_str(line),
+        }
+        previous_blank = blank;
+    }
+
+    *body = collapsed;
+    Ok(())
+}
+
+type Operation = Box<dyn Fn(&mut GemfeedEntry) -> Result<()>>;
+
+/// An ordered list of sanitization operations, applied to each entry
+/// in the order they were added.
+#[derive(Default)]
+pub struct SanitizationPipeline {
+    operations: Vec<Operation>,
+}
+
+impl SanitizationPipeline {
+    pub fn new() -> SanitizationPipeline {
+        Self::default()
+    }
+
+    /// Add any operation to the end of the pipeline.
+    pub fn then<F>(mut self, operation: F) -> SanitizationPipeline
+    where
+        F: Fn(&mut GemfeedEntry) -> Result<()> + 'static,
+    {
+        self.operations.push(Box::new(operation));
+        self
+    }
+
+    pub fn apply_frontmatter(self) -> SanitizationPipeline {
+        self.then(apply_frontmatter)
+    }
+
+    pub fn apply_heading_tags(self, level: usize) -> SanitizationPipeline {
+        self.then(move |entry| apply_heading_tags(entry, level))
+    }
+
+    pub fn strip_before(self, marker: &str) -> SanitizationPipeline {
+        let marker = marker.to_owned();
+        self.then(move |entry| match entry.body_contains(&marker)? {
+            true => strip_before(entry, &marker),
+            false => Ok(()),
+        })
+    }
+
+    pub fn strip_after(self, marker: &str) -> SanitizationPipeline {
+        let marker = marker.to_owned();
+        self.then(move |entry| match entry.body_contains(&marker)? {
+            true => strip_after(entry, &marker),
+            false => Ok(()),
+        })
+    }
+
+    pub fn strip_between(self, start: &str, end: &str) -> SanitizationPipeline {
+        let (start, end) = (start.to_owned(), end.to_owned());
+        self.then(move |entry| strip_between(entry, &start, &end))
+    }
+
+    pub fn strip_links_section(self, marker: &str) -> SanitizationPipeline {
+        let marker = marker.to_owned();
+        self.then(move |entry| strip_links_section(entry, &marker))
+    }
+
+    pub fn strip_lines_matching(self, regex: Regex) -> SanitizationPipeline {
+        self.then(move |entry| strip_lines_matching(entry, &regex))
+    }
+
+    pub fn replace_pattern(self, regex: Regex, replacement: &str) -> SanitizationPipeline {
+        let replacement = replacement.to_owned();
+        self.then(move |entry| replace_pattern(entry, &regex, &replacement))
+    }
+
+    pub fn strip_gemtext_comments(self) -> SanitizationPipeline {
+        self.then(strip_gemtext_comments)
+    }
+
+    pub fn normalize_headings(self) -> SanitizationPipeline {
+        self.then(normalize_gemtext_heading_levels)
+    }
+
+    pub fn convert_bare_urls_to_links(self) -> SanitizationPipeline {
+        self.then(convert_bare_urls_to_links)
+    }
+
+    #[allow(dead_code)]
+    pub fn normalize_whitespace(self) -> SanitizationPipeline {
+        self.then(normalize_whitespace)
+    }
+
+    pub fn collapse_blank_lines(self) -> SanitizationPipeline {
+        self.then(collapse_blank_lines)
+    }
+
+    pub fn truncate_to_word_count(self, max_words: usize, suffix: &str) -> SanitizationPipeline {
+        let suffix = suffix.to_owned();
+        self.then(move |entry| truncate_to_word_count(entry, max_words, &suffix))
+    }
+
+    pub fn truncate_post_to_bytes(self, max_bytes: usize) -> SanitizationPipeline {
+        self.then(move |entry| truncate_post_to_bytes(entry, max_bytes))
+    }
+
+    /// Apply every operation in order, stopping at the first error.
+    pub fn run(&self, entry: &mut GemfeedEntry) -> Result<()> {
+        self.operations
+            .iter()
+            .try_for_each(|operation| operation(entry))
+    }
+}
+
+/// Replace headings deeper than the three levels allowed by Gemtext
+/// (`####` and beyond) with `###`. Lines inside preformatted blocks
+/// are left alone.
+pub fn normalize_gemtext_heading_levels(entry: &mut GemfeedEntry) -> Result<()> {
+    let body = entry.body_mut()?;
+    let mut preformatted = false;
+    let mut normalized = String::with_capacity(body.len());
+
+    for line in body.split_inclusive('\n') {
+        if line.starts_with("```") {
+            preformatted = !preformatted;
+        }
+
+        match line.strip_prefix("####") {
+            Some(rest) if !preformatted => {
+                normalized.push_str("###");
+                normalized.push_str(rest.trim_start_matches('#'));
+            }
+            _ => normalized.push_str(line),
+        }
+    }
+
+    *body = normalized;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word_count(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn truncate_to_word_count_appends_suffix() -> Result<()> {
+        let mut entry =
+            GemfeedEntry::default().with_body("One two three\nfour five six seven".into());
+
+        truncate_to_word_count(&mut entry, 4, DEFAULT_TRUNCATE_SUFFIX)?;
+        let body = entry.body()?;
+
+        assert_eq!(body, "One two three\nfour\n\n*[truncated]*");
+        let truncated = body.strip_suffix(DEFAULT_TRUNCATE_SUFFIX).unwrap();
+        assert!(word_count(truncated) <= 4);
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_bytes_at_limit_unchanged() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("0123456789".into());
+        truncate_to_bytes(&mut entry, 10)?;
+        assert_eq!(entry.body()?, "0123456789");
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_bytes_one_byte_over() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("0123456789A".into());
+        truncate_to_bytes(&mut entry, 10)?;
+
+        let body = entry.body()?;
+        assert_eq!(body, "0123456…");
+        assert_eq!(body.len(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_bytes_keeps_emoji_whole() -> Result<()> {
+        // The 4-byte emoji takes bytes 5..9, across the cut at 7.
+        let mut entry = GemfeedEntry::default().with_body("Hello\u{1F600} world".into());
+        truncate_to_bytes(&mut entry, 10)?;
+
+        let body = entry.body()?;
+        assert_eq!(body, "Hello…");
+        assert!(body.len() <= 10);
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_bytes_backs_up_before_fence() -> Result<()> {
+        let gemtext = "Intro\n```\nlet x = 1;\nlet y = 2;\n```\nOutro\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+        truncate_to_bytes(&mut entry, 24)?;
+        assert_eq!(entry.body()?, "Intro\n…");
+
+        // A block that fits whole is kept.
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+        truncate_to_bytes(&mut entry, 41)?;
+        assert_eq!(
+            entry.body()?,
+            "Intro\n```\nlet x = 1;\nlet y = 2;\n```\nOu…"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_post_to_bytes_counts_markdown_and_hashtags() -> Result<()> {
+        // The Gemtext fits, but not once the hashtag is appended.
+        let mut entry = GemfeedEntry::default().with_body("Some text here\n".into());
+        entry.set_tags(vec!["longtag".into()]);
+        truncate_post_to_bytes(&mut entry, 20)?;
+
+        let markdown = wf::post_body(&entry)?;
+        assert!(markdown.len() <= 20, "{:?}", markdown);
+        assert!(entry.body()?.ends_with(BYTE_TRUNCATION_MARKER));
+        assert!(markdown.ends_with("#longtag\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_word_count_backs_up_before_fence() -> Result<()> {
+        let gemtext = "Intro text\n```\nlet x = 1;\n```\nOutro\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+        truncate_to_word_count(&mut entry, 4, DEFAULT_TRUNCATE_SUFFIX)?;
+        assert_eq!(entry.body()?, "Intro text\n\n\n*[truncated]*");
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_word_count_custom_suffix() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("a b c d e f".into());
+        truncate_to_word_count(&mut entry, 2, " ...")?;
+        assert_eq!(entry.body()?, "a b ...");
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_to_word_count_short_body_unchanged() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("Only three words".into());
+        truncate_to_word_count(&mut entry, 3, DEFAULT_TRUNCATE_SUFFIX)?;
+        assert_eq!(entry.body()?, "Only three words");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_between_single_block() -> Result<()> {
+        let gemtext = "Intro\n<nav>\n=> / Home\n</nav>\nContent\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_between(&mut entry, "<nav>", "</nav>")?;
+        assert_eq!(entry.body()?, "Intro\n\nContent\n");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_between_every_block() -> Result<()> {
+        let gemtext = "One [[a]] two [[b]] three";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_between(&mut entry, "[[", "]]")?;
+        assert_eq!(entry.body()?, "One  two  three");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_between_keeps_adjacent_text() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("before<!--x-->after".into());
+        strip_between(&mut entry, "<!--", "-->")?;
+        assert_eq!(entry.body()?, "beforeafter");
+
+        let mut entry = GemfeedEntry::default().with_body("No markers here".into());
+        strip_between(&mut entry, "<!--", "-->")?;
+        assert_eq!(entry.body()?, "No markers here");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_between_without_end_marker() {
+        let mut entry = GemfeedEntry::default().with_body("a <!--x--> b <!-- c".into());
+        let err = strip_between(&mut entry, "<!--", "-->").unwrap_err();
+        assert!(err.to_string().contains("at byte 13"));
+    }
+
+    #[test]
+    fn strip_links_section_removes_last_section() -> Result<()> {
+        let gemtext =
+            "# Post\n\n## Links\nIntro\n\n## Links\n=> gemini://a.com A\n=> gemini://b.com B\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_links_section(&mut entry, DEFAULT_LINKS_SECTION_MARKER)?;
+        assert_eq!(entry.body()?, "# Post\n\n## Links\nIntro\n");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_links_section_custom_marker() -> Result<()> {
+        let gemtext = "Text\n\n### References\n=> gemini://a.com A\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_links_section(&mut entry, "### References")?;
+        assert_eq!(entry.body()?, "Text\n");
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_blank_lines_without_blank_lines() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("# Title\nOne\nTwo\n".into());
+        collapse_blank_lines(&mut entry)?;
+        assert_eq!(entry.body()?, "# Title\nOne\nTwo\n");
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_blank_lines_keeps_single_blank_line() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("One\n\nTwo\n".into());
+        collapse_blank_lines(&mut entry)?;
+        assert_eq!(entry.body()?, "One\n\nTwo\n");
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_two_blank_lines() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("One\n\n  \nTwo\n".into());
+        collapse_blank_lines(&mut entry)?;
+        assert_eq!(entry.body()?, "One\n\nTwo\n");
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_ten_blank_lines() -> Result<()> {
+        let body = format!("One\n{}Two\n", "\n".repeat(10));
+        let mut entry = GemfeedEntry::default().with_body(body);
+        collapse_blank_lines(&mut entry)?;
+        assert_eq!(entry.body()?, "One\n\nTwo\n");
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_blank_lines_in_mixed_content() -> Result<()> {
+        let gemtext = "# Title\n\n\n\nIntro  \n\n=> gemini://example.com Link\n\t\n\n```\ncode\n\n\nmore\n```\n\n\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        collapse_blank_lines(&mut entry)?;
+        assert_eq!(
+            entry.body()?,
+            "# Title\n\nIntro  \n\n=> gemini://example.com Link\n\n```\ncode\n\n\nmore\n```\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn strip_gemtext_comments_removes_comment_lines() -> Result<()> {
+        let gemtext = "% author: me\n# Title\n%another\nText with 100% effort\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_gemtext_comments(&mut entry)?;
+        assert_eq!(entry.body()?, "# Title\nText with 100% effort\n");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_gemtext_comments_skips_preformatted() -> Result<()> {
+        let gemtext = "```\n% not a comment\n```\n% comment";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_gemtext_comments(&mut entry)?;
+        assert_eq!(entry.body()?, "```\n% not a comment\n```\n");
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_whitespace_collapses_blank_lines() -> Result<()> {
+        let gemtext = "# Title   \n\n\n\nText \t\n\nMore";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        normalize_whitespace(&mut entry)?;
+        assert_eq!(entry.body()?, "# Title\n\nText\n\nMore\n");
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_runs_operations_in_order() -> Result<()> {
+        let gemtext = "Header\n---\nOne two three four\n===\nFooter";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        let pipeline = SanitizationPipeline::new()
+            .strip_before("---\n")
+            .strip_after("===")
+            .truncate_to_word_count(2, "...");
+
+        pipeline.run(&mut entry)?;
+        assert_eq!(entry.body()?, "One two...");
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_stops_on_first_error() {
+        let mut entry = GemfeedEntry::default().with_body("Text".into());
+
+        let pipeline = SanitizationPipeline::new()
+            .then(|_| Err(anyhow::anyhow!("failed")))
+            .then(|entry| {
+                *entry.body_mut()? = "Changed".into();
+                Ok(())
+            });
+
+        assert!(pipeline.run(&mut entry).is_err());
+        assert_eq!(entry.body().unwrap(), "Text");
+    }
+
+    #[test]
+    fn convert_bare_urls_at_line_start() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("https://example.com\nText\n".into());
+        convert_bare_urls_to_links(&mut entry)?;
+        assert_eq!(
+            entry.body()?,
+            "[https://example.com](https://example.com)\nText\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn convert_bare_urls_mid_sentence() -> Result<()> {
+        let mut entry =
+            GemfeedEntry::default().with_body("See http://example.com/a for more.".into());
+        convert_bare_urls_to_links(&mut entry)?;
+        assert_eq!(
+            entry.body()?,
+            "See [http://example.com/a](http://example.com/a) for more."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn convert_bare_urls_keeps_trailing_punctuation_outside() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("Go to https://example.com.".into());
+        convert_bare_urls_to_links(&mut entry)?;
+        assert_eq!(
+            entry.body()?,
+            "Go to [https://example.com](https://example.com)."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn convert_bare_urls_does_not_double_link() -> Result<()> {
+        let gemtext = "[site](https://example.com) and <https://example.org>\n\
+                       => https://example.net Link line\n\
+                       ```\nhttps://example.com\n```\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        convert_bare_urls_to_links(&mut entry)?;
+        assert_eq!(entry.body()?, gemtext);
+        Ok(())
+    }
+
+    #[test]
+    fn convert_bare_urls_in_plain_text_markdown() -> Result<()> {
+        let mut entry = GemfeedEntry::default()
+            .with_body("=> see https://example.net\n".into())
+            .with_mime_type("text/plain");
+
+        convert_bare_urls_to_links(&mut entry)?;
+        assert_eq!(
+            wf::post_body(&entry)?,
+            "=> see [https://example.net](https://example.net)\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extract_frontmatter_strips_block() -> Result<()> {
+        let gemtext = "tags: rust, gemini\nlanguage: en\n\n# Title\nkey: not frontmatter\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        let frontmatter = extract_frontmatter(&mut entry)?;
+        assert_eq!(frontmatter.len(), 2);
+        assert_eq!(frontmatter["tags"], "rust, gemini");
+        assert_eq!(frontmatter["language"], "en");
+        assert_eq!(entry.body()?, "# Title\nkey: not frontmatter\n");
+        Ok(())
+    }
+
+    #[test]
+    fn extract_frontmatter_without_block() -> Result<()> {
+        let gemtext = "# Title\ntags: later\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        assert!(extract_frontmatter(&mut entry)?.is_empty());
+        assert_eq!(entry.body()?, gemtext);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_frontmatter_with_only_tags() -> Result<()> {
+        let gemtext = "tags: rust, , gemini\nText";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        apply_frontmatter(&mut entry)?;
+        assert_eq!(entry.tags(), ["rust", "gemini"]);
+        assert_eq!(entry.language(), None);
+        assert_eq!(entry.body()?, "Text");
+        Ok(())
+    }
+
+    #[test]
+    fn strip_lines_matching_anywhere_in_line() -> Result<()> {
+        let gemtext = "Keep\n--- footer\nAlso keep\nText --- here\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        strip_lines_matching(&mut entry, &Regex::new("---")?)?;
+        assert_eq!(entry.body()?, "Keep\nAlso keep\n");
+        Ok(())
+    }
+
+    #[test]
+    fn replace_pattern_with_groups() -> Result<()> {
+        let gemtext = "=> gemini://example.com/a.gmi A\n=> gemini://example.com/b.gmi B";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        let regex = Regex::new(r"gemini://example\.com/(\w+)\.gmi")?;
+        replace_pattern(&mut entry, &regex, "https://example.com/$1")?;
+        assert_eq!(
+            entry.body()?,
+            "=> https://example.com/a A\n=> https://example.com/b B"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_headings_deeper_than_three() -> Result<()> {
+        let gemtext = "#### Four\n##### Five\n###### Six\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        normalize_gemtext_heading_levels(&mut entry)?;
+        assert_eq!(entry.body()?, "### Four\n### Five\n### Six\n");
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_headings_leaves_valid_levels() -> Result<()> {
+        let gemtext = "# One\n## Two\n### Three\nText with #### inside\n";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        normalize_gemtext_heading_levels(&mut entry)?;
+        assert_eq!(entry.body()?, gemtext);
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_headings_skips_preformatted() -> Result<()> {
+        let gemtext = "```\n#### Not a heading\n```\n#### Heading";
+        let mut entry = GemfeedEntry::default().with_body(gemtext.into());
+
+        normalize_gemtext_heading_levels(&mut entry)?;
+        assert_eq!(entry.body()?, "```\n#### Not a heading\n```\n### Heading");
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, ErrorKind, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The known hosts used for every Gemini request, if set up.
+static KNOWN_HOSTS: OnceCell<Arc<KnownHosts>> = OnceCell::new();
+
+/// Trust-on-first-use store of Gemini server certificates. The first
+/// certificate seen for a host and port is remembered, and later
+/// connections must present the same one. Capsules on other ports of
+/// the same host have certificates of their own.
+///
+/// The file has one `host:port fingerprint` pair per line.
+pub struct KnownHosts {
+    path: PathBuf,
+    trust_new: bool,
+    hosts: Mutex<BTreeMap<String, String>>,
+}
+
+impl KnownHosts {
+    /// ~/.local/share/gemfreely/known_hosts, or the same under
+    /// $XDG_DATA_HOME if it is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+        };
+
+        Some(data_dir.join("gemfreely").join("known_hosts"))
+    }
+
+    /// Read the known hosts file, if it exists. With `trust_new`,
+    /// certificates of hosts not in the file are accepted without
+    /// asking.
+    pub fn load(path: &Path, trust_new: bool) -> Result<KnownHosts> {
+        let hosts = match fs::read_to_string(path) {
+            Ok(text) => parse_known_hosts(&text),
+            Err(err) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(KnownHosts {
+            path: path.to_owned(),
+            trust_new,
+            hosts: Mutex::new(hosts),
+        })
+    }
+
+    /// Check a DER-encoded server certificate of `host` (as
+    /// `host:port`).
+    pub fn verify_cert(&self, host: &str, cert_der: &[u8]) -> Result<()> {
+        self.verify(host, &fingerprint(cert_der))
+    }
+
+    /// Check the fingerprint of a certificate presented by the host,
+    /// remembering it if the host has not been seen before.
+    pub fn verify(&self, host: &str, fingerprint: &str) -> Result<()> {
+        let mut hosts = self.hosts.lock().expect("known hosts lock poisoned");
+
+        match hosts.get(host) {
+            Some(known) if known == fingerprint => Ok(()),
+            Some(known) => Err(anyhow!(
+                "Certificate for {} has changed [known={}, presented={}]. \
+                 If the capsule really has a new certificate, remove the \
+                 line for {} from {}",
+                host,
+                known,
+                fingerprint,
+                host,
+                self.path.display()
+            )),
+            None => {
+                if !self.trust_new && !confirm_new_host(host, fingerprint)? {
+                    return Err(anyhow!(
+                        "Certificate for {} not trusted. Use --trust-new-certs \
+                         to accept certificates of new hosts",
+                        host
+                    ));
+                }
+
+                hosts.insert(host.to_owned(), fingerprint.to_owned());
+                self.save(&hosts)
+            }
+        }
+    }
+
+    fn save(&self, hosts: &BTreeMap<String, String>) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let lines: String = hosts
+            .iter()
+            .map(|(host, fingerprint)| format!("{} {}\n", host, fingerprint))
+            .collect();
+
+        fs::write(&self.path, lines)?;
+        Ok(())
+    }
+}
+
+fn parse_known_hosts(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.trim().split_once(' '))
+        .map(|(host, fingerprint)| (host.to_owned(), fingerprint.trim().to_owned()))
+        .collect()
+}
+
+/// Ask on the terminal whether to trust a new host. Without a
+/// terminal to ask on, the host is not trusted.
+fn confirm_new_host(host: &str, fingerprint: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    eprint!(
+        "First connection to {} [fingerprint={}]. Trust this certificate? [y/N] ",
+        host, fingerprint
+    );
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// SHA-256 fingerprint of a DER-encoded certificate, in the same
+/// `SHA256:<hex>` form as the Atom cert-fingerprint extension.
+pub fn fingerprint(cert_der: &[u8]) -> String {
+    let hex: String = Sha256::digest(cert_der)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("SHA256:{}", hex)
+}
+
+/// Use the known hosts for all Gemini requests from now on.
+pub fn init(known_hosts: KnownHosts) {
+    let _ = KNOWN_HOSTS.set(Arc::new(known_hosts));
+}
+
+/// The known hosts set up by init(), if any.
+pub fn known_hosts() -> Option<Arc<KnownHosts>> {
+    KNOWN_HOSTS.get().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gemfreely-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn first_encounter_is_recorded() -> Result<()> {
+        let path = temp_path("known-hosts-first");
+        KnownHosts::load(&path, true)?.verify("example.com:1965", "SHA256:aa")?;
+
+        assert_eq!(fs::read_to_string(&path)?, "example.com:1965 SHA256:aa\n");
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn matching_re_encounter() -> Result<()> {
+        let path = temp_path("known-hosts-match");
+        fs::write(&path, "example.com:1965 SHA256:aa\n")?;
+
+        let result = KnownHosts::load(&path, false)?.verify("example.com:1965", "SHA256:aa");
+        fs::remove_file(&path)?;
+        result
+    }
+
+    #[test]
+    fn mismatched_re_encounter() -> Result<()> {
+        let path = temp_path("known-hosts-mismatch");
+        fs::write(&path, "example.com:1965 SHA256:aa\n")?;
+
+        // Trusting new certificates does not extend to changed ones.
+        let result = KnownHosts::load(&path, true)?.verify("example.com:1965", "SHA256:bb");
+        let contents = fs::read_to_string(&path)?;
+        fs::remove_file(&path)?;
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("known=SHA256:aa"));
+        assert!(err.contains("presented=SHA256:bb"));
+        assert!(err.contains("remove the line for example.com:1965"));
+        assert_eq!(contents, "example.com:1965 SHA256:aa\n");
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_is_sha256_hex() {
+        assert_eq!(
+            fingerprint(b""),
+            "SHA256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Keeps a plain text record of which WriteFreely post ID was created
+/// for which Gemfeed slug. Each line has the form `slug=<slug>
+/// id=<post_id>`, and later lines win if a slug appears more than
+/// once.
+pub struct PostIdTracker {
+    path: PathBuf,
+}
+
+#[allow(dead_code)]
+impl PostIdTracker {
+    pub fn new<P: AsRef<Path>>(path: P) -> PostIdTracker {
+        PostIdTracker {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// Append a slug to post ID mapping to the tracking file, creating
+    /// the file if necessary.
+    pub fn record(&self, slug: &str, post_id: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "slug={} id={}", slug, post_id)?;
+        Ok(())
+    }
+
+    /// Read all recorded mappings of slug to post ID. A missing
+    /// tracking file is the same as an empty one.
+    pub fn load(&self) -> Result<HashMap<String, String>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(parse_line)
+            .collect()
+    }
+}
+
+fn parse_line(line: &str) -> Result<(String, String)> {
+    let mut parts = line.split_whitespace();
+    let slug = parts.next().and_then(|part| part.strip_prefix("slug="));
+    let id = parts.next().and_then(|part| part.strip_prefix("id="));
+
+    match (slug, id) {
+        (Some(slug), Some(id)) => Ok((slug.to_owned(), id.to_owned())),
+        _ => Err(anyhow!("Invalid post ID tracking line: {}", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gemfreely-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn record_and_load_post_ids() -> Result<()> {
+        let path = temp_path("record-and-load");
+        let tracker = PostIdTracker::new(&path);
+
+        tracker.record("post1", "abc123")?;
+        tracker.record("post2", "def456")?;
+
+        let ids = tracker.load()?;
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "slug=post1 id=abc123\nslug=post2 id=def456\n"
+        );
+        assert_eq!(ids.get("post1").map(String::as_str), Some("abc123"));
+        assert_eq!(ids.get("post2").map(String::as_str), Some("def456"));
+
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn load_missing_file_is_empty() -> Result<()> {
+        let tracker = PostIdTracker::new(temp_path("missing"));
+        assert!(tracker.load()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid_line_fails() {
+        assert!(parse_line("post1 abc123").is_err());
+    }
+}
@@ -1,45 +1,142 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures_util::future::join_all;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
 use std::result::Result as StdResult;
+use tokio::sync::Semaphore;
+use tracing::debug;
 use url::Url;
 
 use writefreely_client::{
-    post::{Post, PostCreateRequest},
+    collections::posts::SlugOrId,
+    error::Error as WriteFreelyError,
+    post::{Post, PostCreateRequest, PostId, Slug},
     Client, Timestamp,
 };
 
 use crate::gemfeed::GemfeedEntry;
+use crate::retry::{self, with_retry_when};
+use crate::Cli;
+use WriteFreelyCredentials::*;
+
+/// Pages of collection posts fetched before giving up, in case a
+/// buggy server never returns a last page.
+pub const DEFAULT_MAX_SLUG_PAGES: usize = 1000;
 
 /// Wrapper struct for managing the WriteFreely connection.
+///
+/// WriteFreely has two kinds of posts. Collection posts belong to a
+/// blog (the alias) and are addressed by slug within it. Anonymous
+/// posts belong to no collection and only have a post ID. Every post
+/// has an ID, so looking up by ID works for both kinds.
 pub struct WriteFreely {
     client: Client,
-    alias: String,
+
+    /// The collection posts are listed in and created in.
+    collection: String,
+    max_retries: u32,
+    max_slug_pages: usize,
+}
+
+/// A collection (blog) of the logged in user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CollectionInfo {
+    pub alias: String,
+    pub title: String,
+    pub total_posts: usize,
 }
 
 pub enum WriteFreelyCredentials<'a> {
     UsernameAndPassword(&'a str, &'a str),
     AccessToken(&'a str),
+
+    /// An OAuth 2.0 bearer token. WriteFreely currently accepts these
+    /// in the same way as access tokens, but OAuth flows (such as
+    /// refreshing) will hook in here.
+    OAuthToken(&'a str),
+}
+
+/// Passwords and tokens are never printed, even in debug logs.
+impl fmt::Debug for WriteFreelyCredentials<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsernameAndPassword(user, _) => write!(f, "UsernameAndPassword({}, [REDACTED])", user),
+            AccessToken(_) => write!(f, "AccessToken([REDACTED])"),
+            OAuthToken(_) => write!(f, "OAuthToken([REDACTED])"),
+        }
+    }
+}
+
+/// Token credentials from the global CLI options.
+impl<'a> TryFrom<&'a Cli> for WriteFreelyCredentials<'a> {
+    type Error = anyhow::Error;
+
+    fn try_from(cli: &'a Cli) -> StdResult<Self, Self::Error> {
+        match (&cli.wf_access_token, &cli.oauth_token) {
+            (Some(token), _) => Ok(WriteFreelyCredentials::AccessToken(token)),
+            (None, Some(token)) => Ok(WriteFreelyCredentials::OAuthToken(token)),
+            (None, None) => Err(anyhow!("WriteFreely access token required")),
+        }
+    }
 }
 
 #[allow(dead_code)]
 impl WriteFreely {
-    /// Attempts to create and log in to the WriteFreely server.
+    /// Attempts to create and log in to the WriteFreely server. The
+    /// alias of the user is also the collection posts go to, unless
+    /// with_collection() says otherwise.
     pub async fn new(
         url: &Url,
         alias: &str,
         creds: &WriteFreelyCredentials<'_>,
     ) -> Result<WriteFreely> {
-        use WriteFreelyCredentials::*;
+        debug!(
+            "Connecting to WriteFreely: {} [alias={}, creds={:?}]",
+            url, alias, creds
+        );
         let client = match creds {
             UsernameAndPassword(user, pw) => Client::new(url)?.login(user, pw).await?,
-            AccessToken(token) => Client::new(url)?.with_token(token),
+            AccessToken(token) | OAuthToken(token) => Client::new(url)?.with_token(token),
         };
 
         Ok(WriteFreely {
             client,
-            alias: alias.to_owned(),
+            collection: alias.to_owned(),
+            max_retries: 0,
+            max_slug_pages: DEFAULT_MAX_SLUG_PAGES,
         })
     }
 
+    /// Consumes self to set how often creating a post is retried.
+    pub fn with_max_retries(self, max_retries: u32) -> WriteFreely {
+        WriteFreely {
+            max_retries,
+            ..self
+        }
+    }
+
+    /// Consumes self to work with another collection of the user than
+    /// the one named by their alias.
+    pub fn with_collection(self, collection: &str) -> WriteFreely {
+        WriteFreely {
+            collection: collection.to_owned(),
+            ..self
+        }
+    }
+
+    /// Consumes self to set how many pages of collection posts are
+    /// fetched at most.
+    pub fn with_max_slug_pages(self, max_slug_pages: usize) -> WriteFreely {
+        WriteFreely {
+            max_slug_pages,
+            ..self
+        }
+    }
+
     pub async fn user(&self) -> Result<String> {
         Ok(self.client.get_authenticated_user().await?)
     }
@@ -55,9 +152,161 @@ impl WriteFreely {
         Ok(())
     }
 
+    /// Get all posts in the collection, a page at a time until a page
+    /// is empty, shorter than the ones before, or only has posts
+    /// already seen. writefreely_client's list() works out the number
+    /// of pages from the collection's post count and a page size of
+    /// 10, which misses posts if either is off.
+    pub async fn posts(&self) -> Result<Vec<Post>> {
+        #[derive(Deserialize)]
+        struct CollectionPosts {
+            posts: Vec<Post>,
+        }
+
+        let mut posts = vec![];
+        let mut seen_ids = HashSet::new();
+        let mut page_size = 0;
+
+        for page in 1..=self.max_slug_pages {
+            let uri = format!("/collections/{}/posts?page={}", self.collection, page);
+            let page_posts = match self.client.api().get::<CollectionPosts, _>(&uri).await {
+                Ok(collection) => collection.posts,
+                // Some servers answer a page past the end with a 404.
+                Err(WriteFreelyError::ApiError(404, _)) if page > 1 => return Ok(posts),
+                Err(err) => return Err(err.into()),
+            };
+
+            let fetched = page_posts.len();
+            page_size = page_size.max(fetched);
+            let before = posts.len();
+            posts.extend(
+                page_posts
+                    .into_iter()
+                    .filter(|post| seen_ids.insert(post.id.to_string())),
+            );
+
+            if fetched == 0 || fetched < page_size || posts.len() == before {
+                return Ok(posts);
+            }
+        }
+
+        Err(anyhow!(
+            "Gave up listing the posts of collection {} after {} pages",
+            self.collection,
+            self.max_slug_pages
+        ))
+    }
+
+    /// Every collection of the logged in user, not just the alias.
+    /// /me/collections leaves out the post counts on some servers, so
+    /// those collections are fetched one by one for their count.
+    pub async fn list_collections(&self) -> Result<Vec<CollectionInfo>> {
+        let collections = self.client.collections();
+        let mut infos = vec![];
+
+        for collection in collections.list().await? {
+            let total_posts = match collection.total_posts {
+                Some(count) => count,
+                None => collections
+                    .get(&collection.alias)
+                    .await?
+                    .total_posts
+                    .unwrap_or_default(),
+            };
+
+            infos.push(CollectionInfo {
+                alias: collection.alias,
+                title: collection.title,
+                total_posts,
+            });
+        }
+
+        Ok(infos)
+    }
+
+    /// Create a new collection for the logged in user. WriteFreely
+    /// answers with a 409 if the alias is taken.
+    pub async fn create_collection(
+        &self,
+        alias: &str,
+        title: &str,
+        description: Option<&str>,
+    ) -> Result<()> {
+        self.request_collection(alias, title, description)
+            .await
+            .inspect_err(log_api_error)?;
+        Ok(())
+    }
+
+    /// Like create_collection(), but a collection that already exists
+    /// is not an error. Returns whether the collection was created.
+    pub async fn create_collection_if_missing(
+        &self,
+        alias: &str,
+        title: &str,
+        description: Option<&str>,
+    ) -> Result<bool> {
+        match self.request_collection(alias, title, description).await {
+            Ok(()) => Ok(true),
+            Err(WriteFreelyError::ApiError(409, _)) => Ok(false),
+            Err(err) => {
+                log_api_error(&err);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// writefreely_client's create() cannot send a description.
+    async fn request_collection(
+        &self,
+        alias: &str,
+        title: &str,
+        description: Option<&str>,
+    ) -> StdResult<(), WriteFreelyError> {
+        #[derive(Serialize)]
+        struct CreateCollection<'a> {
+            alias: &'a str,
+            title: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            description: Option<&'a str>,
+        }
+
+        let request = CreateCollection {
+            alias,
+            title,
+            description,
+        };
+
+        self.client
+            .api()
+            .post::<IgnoredAny, _, _>("/collections", request)
+            .await?;
+        Ok(())
+    }
+
+    /// Slugs of the user's drafts: posts that belong to no collection.
+    pub async fn draft_slugs(&self) -> Result<Vec<String>> {
+        let slugs = self
+            .drafts()
+            .await?
+            .into_iter()
+            .flat_map(|post| post.slug)
+            .map(|slug| slug.to_string())
+            .collect();
+        Ok(slugs)
+    }
+
+    async fn drafts(&self) -> Result<Vec<Post>> {
+        let posts = self.client.posts().list().await?;
+        Ok(posts
+            .into_iter()
+            .filter(|post| post.collection.is_none())
+            .collect())
+    }
+
     /// Get the slugs on the server for the alias/user.
     pub async fn slugs(&self) -> Result<Vec<String>> {
-        let posts = self.client.collections().posts(&self.alias).list().await?;
+        let posts = self.posts().await?;
         let slugs: Vec<_> = posts
             .into_iter()
             .flat_map(|post| post.slug)
@@ -66,11 +315,331 @@ impl WriteFreely {
         Ok(slugs)
     }
 
-    pub async fn create_post(&self, entry: &GemfeedEntry) -> Result<Post> {
-        let blog = self.client.collections().posts(&self.alias);
-        let post = blog.create(entry.try_into()?).await?;
+    /// Render the posts in the collection as a Gemfeed index page, so
+    /// WriteFreely content can be mirrored back to Gemini. The page is
+    /// headed by the title of the collection, or its alias if untitled.
+    pub async fn collection_posts_as_gemfeed(&self) -> Result<String> {
+        let collection = self.client.collections().get(&self.collection).await?;
+        let title = match collection.title.trim() {
+            "" => &self.collection,
+            title => title,
+        };
+
+        render_gemfeed(title, &self.posts().await?)
+    }
+
+    /// Check whether a post with the given ID exists on the server,
+    /// regardless of which collection (if any) it belongs to.
+    pub async fn post_exists_by_id(&self, id: &str) -> Result<bool> {
+        let result = self.client.posts().get(PostId::from(id)).await;
+        exists_from_result(result)
+    }
+
+    /// Move an existing post, anonymous or not, into the given
+    /// collection. Returns the post as it is after the move.
+    pub async fn move_post_to_collection(
+        &self,
+        post_id: &str,
+        collection_alias: &str,
+    ) -> Result<Post> {
+        let id = PostId::from(post_id);
+        let blog = self.client.collections().posts(collection_alias);
+        blog.collect(vec![SlugOrId::Id(id.clone())]).await?;
+        Ok(self.client.posts().get(id).await?)
+    }
+
+    /// Move a post out of its collection, into the drafts of the user.
+    /// The post keeps its ID and slug.
+    pub async fn move_post_to_drafts(&self, post_id: &str) -> Result<()> {
+        let _: serde_json::Value = self
+            .client
+            .api()
+            .post("/posts/disperse", vec![post_id])
+            .await
+            .inspect_err(log_api_error)?;
+        Ok(())
+    }
+
+    /// Move a collection post to the drafts of the user, or publish a
+    /// draft with the given slug in the collection. WriteFreely has no
+    /// separate visibility flag; a draft is a post in no collection.
+    pub async fn set_draft_status(&self, slug: &str, is_draft: bool) -> Result<()> {
+        if is_draft {
+            let post = self.get_post_by_slug(slug).await?;
+            return self.move_post_to_drafts(&post.id.to_string()).await;
+        }
+
+        let draft = self
+            .drafts()
+            .await?
+            .into_iter()
+            .find(|post| post.slug.as_ref().is_some_and(|s| s.to_string() == slug))
+            .ok_or(anyhow!("No draft with slug: {}", slug))?;
+
+        self.move_post_to_collection(&draft.id.to_string(), &self.collection)
+            .await?;
+        Ok(())
+    }
+
+    /// Get a collection post as it currently is on the server.
+    pub async fn get_post_by_slug(&self, slug: &str) -> Result<Post> {
+        let blog = self.client.collections().posts(&self.collection);
+        Ok(blog.get(SlugOrId::Slug(Slug::from(slug))).await?)
+    }
+
+    /// Get the Markdown body of a collection post as WriteFreely
+    /// stores it, for comparison with the converted Gemlog body.
+    pub async fn fetch_post_markdown(&self, slug: &str) -> Result<String> {
+        Ok(self.get_post_by_slug(slug).await?.body)
+    }
+
+    /// Every post in the collection with its full body. Posts listed
+    /// without a body are fetched one by one.
+    pub async fn get_all_posts(&self) -> Result<Vec<Post>> {
+        let mut posts = self.posts().await?;
+        for post in posts.iter_mut().filter(|post| post.body.is_empty()) {
+            if let Some(slug) = &post.slug {
+                *post = self.get_post_by_slug(&slug.to_string()).await?;
+            }
+        }
+        Ok(posts)
+    }
+
+    /// Replace the title, body and date of an existing collection post
+    /// with those of the Gemlog entry.
+    pub async fn update_post(&self, slug: &str, entry: &GemfeedEntry) -> Result<Post> {
+        let blog = self.client.collections().posts(&self.collection);
+        let post = blog
+            .update(SlugOrId::Slug(Slug::from(slug)), entry.try_into()?)
+            .await
+            .inspect_err(log_api_error)?;
         Ok(post)
     }
+
+    /// Change the slug of a collection post in place, so it keeps its
+    /// post ID. WriteFreely updates posts as a whole, so the current
+    /// body and metadata are sent back along with the new slug.
+    pub async fn rename_post_slug(&self, old_slug: &str, new_slug: &str) -> Result<Post> {
+        let blog = self.client.collections().posts(&self.collection);
+        let post = blog.get(SlugOrId::Slug(Slug::from(old_slug))).await?;
+        let id = post.id.clone();
+        let post = blog
+            .update(SlugOrId::Id(id), rename_request(post, new_slug))
+            .await?;
+        Ok(post)
+    }
+
+    /// Delete a collection post.
+    pub async fn delete_post(&self, slug: &str) -> Result<()> {
+        let blog = self.client.collections().posts(&self.collection);
+        blog.delete(SlugOrId::Slug(Slug::from(slug)))
+            .await
+            .inspect_err(log_api_error)?;
+        Ok(())
+    }
+
+    /// Create a collection post or, with `draft`, a post in no
+    /// collection: a draft of the user, never public until it is
+    /// collected by the publish command. Creating a post twice makes
+    /// two posts, so only requests that never reached the server are
+    /// retried.
+    pub async fn create_post(&self, entry: &GemfeedEntry, draft: bool) -> Result<Post> {
+        let request: PostCreateRequest = entry.try_into()?;
+        let uri = match draft {
+            true => "/posts".to_owned(),
+            false => format!("/collections/{}/posts", self.collection),
+        };
+
+        with_retry_when(
+            self.max_retries,
+            retry::BASE_DELAY_SECS,
+            is_connect_failure,
+            || async {
+                let post = self
+                    .client
+                    .api()
+                    .post(&uri, &request)
+                    .await
+                    .inspect_err(log_api_error)?;
+                Ok(post)
+            },
+        )
+        .await
+    }
+
+    /// Create posts for all entries, each with whether it is a draft,
+    /// with at most `concurrency` requests in flight at once. Every
+    /// entry is attempted; results are returned in the same order as
+    /// the entries.
+    pub async fn bulk_create_posts(
+        &self,
+        entries: &[(&GemfeedEntry, bool)],
+        concurrency: usize,
+    ) -> Vec<Result<Post>> {
+        run_bounded(entries, concurrency, |&(entry, draft)| {
+            self.create_post(entry, draft)
+        })
+        .await
+    }
+}
+
+/// Run the operation on every item concurrently, with at most `limit`
+/// operations running at the same time.
+async fn run_bounded<T, F, Fut>(items: &[T], limit: usize, operation: F) -> Vec<Fut::Output>
+where
+    F: Fn(&T) -> Fut,
+    Fut: Future,
+{
+    let semaphore = Semaphore::new(limit.max(1));
+    let tasks = items.iter().map(|item| async {
+        let _permit = semaphore.acquire().await.expect("Semaphore closed");
+        operation(item).await
+    });
+
+    join_all(tasks).await
+}
+
+/// An update request for the post that only differs in its slug.
+fn rename_request(post: Post, new_slug: &str) -> PostCreateRequest {
+    let req = PostCreateRequest::new()
+        .slug(Slug::from(new_slug))
+        .body(post.body)
+        .appearance(post.appearance)
+        .rtl(post.rtl);
+
+    let req = match post.title {
+        Some(title) => req.title(title),
+        _ => req,
+    };
+
+    match post.language {
+        Some(lang) => req.lang(lang),
+        _ => req,
+    }
+}
+
+/// The Markdown body sent to WriteFreely for the entry.
+pub fn post_body(entry: &GemfeedEntry) -> Result<String> {
+    Ok(with_hashtags(entry.body_as_markdown()?, entry.tags()))
+}
+
+/// WriteFreely has no tags field; it takes the tags of a post from
+/// the hashtags in its body. Hashtags cannot contain spaces.
+fn with_hashtags(markdown: String, tags: &[String]) -> String {
+    let hashtags: Vec<_> = tags
+        .iter()
+        .map(|tag| tag.trim_start_matches('#').replace(char::is_whitespace, ""))
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| format!("#{}", tag))
+        .collect();
+
+    match hashtags.is_empty() {
+        true => markdown,
+        false => format!("{}\n\n{}\n", markdown.trim_end(), hashtags.join(" ")),
+    }
+}
+
+fn log_api_error(err: &WriteFreelyError) {
+    match err {
+        WriteFreelyError::ApiError(code, message) => {
+            debug!(
+                "WriteFreely API error: [code={}, message={}]",
+                code, message
+            )
+        }
+        _ => debug!("WriteFreely request failed: {}", err),
+    }
+}
+
+/// Whether the request failed before reaching the server, so sending
+/// it again cannot repeat what the server already did.
+fn is_connect_failure(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<WriteFreelyError>(),
+        Some(WriteFreelyError::RequestError(err)) if err.is_connect()
+    )
+}
+
+/// A 404 from the API means the post does not exist; any other error
+/// is a real failure.
+fn exists_from_result<T>(result: StdResult<T, WriteFreelyError>) -> Result<bool> {
+    if let Err(ref err) = result {
+        log_api_error(err);
+    }
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(WriteFreelyError::ApiError(404, _)) => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Timestamp has no accessor for the date it wraps, but it serializes
+/// to the WriteFreely date format, which can be parsed back.
+pub fn timestamp_to_datetime(timestamp: &Timestamp) -> Result<DateTime<Utc>> {
+    let value = serde_json::to_value(timestamp)?;
+    let text = value
+        .as_str()
+        .ok_or(anyhow!("Invalid timestamp: {}", value))?;
+    Ok(NaiveDateTime::parse_from_str(text, "%F %T")?.and_utc())
+}
+
+/// Gemtext Gemfeed listing of the posts, newest first. Posts need a
+/// slug and a creation date to be listed; untitled posts are listed
+/// under their slug.
+fn render_gemfeed(title: &str, posts: &[Post]) -> Result<String> {
+    let mut links = vec![];
+    for post in posts {
+        let created = post.created.as_ref().map(timestamp_to_datetime);
+        if let (Some(slug), Some(created)) = (&post.slug, created) {
+            let slug = slug.to_string();
+            let post_title = post.title.clone().unwrap_or_else(|| slug.clone());
+            links.push((created?, slug, post_title));
+        }
+    }
+
+    links.sort_by_key(|(created, ..)| std::cmp::Reverse(*created));
+
+    let mut gemfeed = format!("# {}\n\n", title);
+    for (created, slug, post_title) in links {
+        gemfeed.push_str(&format!(
+            "=> /{} {} {}\n",
+            slug,
+            created.format("%Y-%m-%d"),
+            post_title
+        ));
+    }
+
+    Ok(gemfeed)
+}
+
+/// Maps a WriteFreely post back to a Gemfeed entry, with the body
+/// already loaded as the Markdown stored on WriteFreely. The entry URL
+/// is the post's URL on the WriteFreely instance.
+impl TryFrom<&Post> for GemfeedEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(post: &Post) -> StdResult<Self, Self::Error> {
+        let slug = post
+            .slug
+            .as_ref()
+            .ok_or(anyhow!("Post {} has no slug", post.id))?;
+
+        let url = post
+            .url
+            .as_deref()
+            .ok_or(anyhow!("Post {} has no URL", post.id))?;
+
+        let published = post
+            .created
+            .as_ref()
+            .map(timestamp_to_datetime)
+            .transpose()?;
+        let title = post.title.as_deref().unwrap_or_default();
+
+        let entry = GemfeedEntry::new(Url::parse(url)?, &slug.to_string(), title, published);
+        Ok(entry.with_body(post.body.clone()))
+    }
 }
 
 impl TryFrom<GemfeedEntry> for PostCreateRequest {
@@ -85,23 +654,31 @@ impl TryFrom<&GemfeedEntry> for PostCreateRequest {
     type Error = anyhow::Error;
 
     fn try_from(entry: &GemfeedEntry) -> StdResult<Self, Self::Error> {
-        let published = entry.published().map(|date| Timestamp::from(*date));
+        let published = entry
+            .republish_date()
+            .or(entry.published())
+            .map(|date| Timestamp::from(*date));
         let req = PostCreateRequest::new()
             .slug(entry.slug().into())
             .title(entry.title())
-            .body(entry.body_as_markdown()?);
+            .body(post_body(entry)?);
 
         let req = match published {
             Some(publish_date) => req.created(publish_date),
             _ => req,
         };
 
+        let req = match entry.language() {
+            Some(lang) => req.lang(lang),
+            _ => req,
+        };
+
         Ok(req)
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     #[test]
     fn tryfrom_to_request_handles_gt_lt() {
@@ -119,4 +696,481 @@ mod tests {
         let result = PostCreateRequest::try_from(entry);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn tryfrom_to_request_uses_republish_date() -> Result<()> {
+        let republish_date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .and_then(|date| date.and_hms_opt(8, 30, 0))
+            .map(|date| date.and_utc());
+
+        let mut entry = GemfeedEntry::default().with_body("Text".into());
+        entry.set_republish_date(republish_date);
+
+        let req = PostCreateRequest::try_from(&entry)?;
+        let json = serde_json::to_value(req)?;
+        assert_eq!(json["created"], "2024-05-01 08:30:00");
+        Ok(())
+    }
+
+    #[test]
+    fn tryfrom_to_request_adds_tags_and_language() -> Result<()> {
+        let mut entry = GemfeedEntry::default().with_body("Text\n".into());
+        entry.set_tags(vec!["rust".into(), "gemini protocol".into()]);
+        entry.set_language(Some("de".into()));
+
+        let json = serde_json::to_value(PostCreateRequest::try_from(&entry)?)?;
+        assert_eq!(json["body"], "Text\n\n#rust #geminiprotocol\n");
+        assert_eq!(json["lang"], "de");
+        Ok(())
+    }
+
+    #[test]
+    fn credentials_debug_is_redacted() {
+        let creds = WriteFreelyCredentials::AccessToken("secret-token");
+        assert_eq!(format!("{:?}", creds), "AccessToken([REDACTED])");
+
+        let creds = WriteFreelyCredentials::UsernameAndPassword("me", "hunter2");
+        assert!(!format!("{:?}", creds).contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn fetch_post_markdown_from_api_response() -> Result<()> {
+        // A WriteFreely GET /api/collections/{alias}/posts/{slug}.
+        let json = r###"{"code": 200, "data": {
+            "id": "rf3t35fkax0aw",
+            "slug": "my-post",
+            "appearance": "norm",
+            "language": "en",
+            "rtl": false,
+            "created": "2024-03-01T12:00:00Z",
+            "updated": "2024-03-01T12:00:00Z",
+            "title": "My Post",
+            "body": "# Heading\n\nSome *Markdown* text.",
+            "tags": [],
+            "views": 3
+        }}"###;
+
+        let url = serve_fixed_response(200, json)?;
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token")).await?;
+        assert_eq!(
+            wf.fetch_post_markdown("my-post").await?,
+            "# Heading\n\nSome *Markdown* text."
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_post_markdown_of_missing_post() -> Result<()> {
+        let url = serve_fixed_response(404, r#"{"code":404,"error_msg":"Post not found."}"#)?;
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token")).await?;
+
+        let err = wf.fetch_post_markdown("missing").await.unwrap_err();
+        assert!(err.to_string().contains("404"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_request_keeps_post_content() -> Result<()> {
+        let json = r#"{
+            "id": "rf3t35fkax0aw",
+            "slug": "2024-03-01-my-post",
+            "appearance": "norm",
+            "language": "en",
+            "rtl": false,
+            "title": "My Post",
+            "body": "Body text",
+            "tags": [],
+            "views": 0
+        }"#;
+
+        let post: Post = serde_json::from_str(json)?;
+        let req = serde_json::to_value(rename_request(post, "my-post"))?;
+
+        assert_eq!(req["slug"], "my-post");
+        assert_eq!(req["body"], "Body text");
+        assert_eq!(req["title"], "My Post");
+        assert_eq!(req["lang"], "en");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_bounded_attempts_all_items() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let attempts = AtomicUsize::new(0);
+        let results = run_bounded(&[1, 2, 3, 4], 2, |n| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            let n = *n;
+            async move {
+                match n {
+                    2 => Err(anyhow!("post {} failed", n)),
+                    _ => Ok(n),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+        assert_eq!(results.len(), 4);
+        assert!(results[1].is_err());
+        assert_eq!(results[3].as_ref().unwrap(), &4);
+    }
+
+    #[tokio::test]
+    async fn run_bounded_respects_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+
+        run_bounded(&[0; 6], 2, |_| async {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+        })
+        .await;
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn render_gemfeed_newest_first() -> Result<()> {
+        let json = r#"[
+            {"id": "a", "slug": "older", "title": "Older Post", "body": "",
+             "appearance": "norm", "language": "en", "rtl": false,
+             "tags": [], "views": 0, "created": "2024-01-05T10:00:00Z"},
+            {"id": "b", "slug": "newer", "title": "", "body": "",
+             "appearance": "norm", "language": "en", "rtl": false,
+             "tags": [], "views": 0, "created": "2024-02-10T10:00:00Z"},
+            {"id": "c", "title": "", "body": "", "appearance": "norm", "language": "en",
+             "rtl": false, "tags": [], "views": 0, "created": "2024-03-01T10:00:00Z"}
+        ]"#;
+
+        let posts: Vec<Post> = serde_json::from_str(json)?;
+        let gemfeed = render_gemfeed("my-blog", &posts)?;
+
+        assert_eq!(
+            gemfeed,
+            "# my-blog\n\n=> /newer 2024-02-10 newer\n=> /older 2024-01-05 Older Post\n"
+        );
+
+        // The output must parse as a Gemfeed again.
+        let url = Url::parse("gemini://example.com/")?;
+        let settings = crate::gemfeed::GemfeedParserSettings::default();
+        let parsed = crate::gemfeed::Gemfeed::load_from_reader(
+            &url,
+            gemfeed.as_bytes(),
+            "text/gemini",
+            &settings,
+        )?;
+        assert_eq!(parsed.slugs(), vec!["newer", "older"]);
+        Ok(())
+    }
+
+    #[test]
+    fn gemfeed_entry_from_post() -> Result<()> {
+        let json = r#"{
+            "id": "rf3t35fkax0aw",
+            "slug": "my-post",
+            "appearance": "norm",
+            "language": "en",
+            "rtl": false,
+            "created": "2024-03-01T12:30:00Z",
+            "title": "My Post",
+            "body": "Some *Markdown* text.",
+            "url": "https://blog.example.com/user/my-post",
+            "tags": [],
+            "views": 0
+        }"#;
+
+        let post: Post = serde_json::from_str(json)?;
+        let entry = GemfeedEntry::try_from(&post)?;
+
+        let expected_date = chrono::NaiveDate::from_ymd_opt(2024, 3, 1)
+            .and_then(|date| date.and_hms_opt(12, 30, 0))
+            .map(|date| date.and_utc());
+
+        assert_eq!(entry.slug(), "my-post");
+        assert_eq!(entry.title(), "My Post");
+        assert_eq!(entry.published().copied(), expected_date);
+        assert_eq!(
+            entry.url().as_str(),
+            "https://blog.example.com/user/my-post"
+        );
+        assert_eq!(entry.body_if_loaded(), Some("Some *Markdown* text."));
+        Ok(())
+    }
+
+    #[test]
+    fn gemfeed_entry_from_post_requires_slug() -> Result<()> {
+        let json = r#"{
+            "id": "rf3t35fkax0aw",
+            "appearance": "norm",
+            "language": "",
+            "rtl": false,
+            "title": "",
+            "body": "Text",
+            "url": "https://blog.example.com/rf3t35fkax0aw",
+            "tags": [],
+            "views": 0
+        }"#;
+
+        let post: Post = serde_json::from_str(json)?;
+        assert!(GemfeedEntry::try_from(&post).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn credentials_from_oauth_token() -> Result<()> {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "gemfreely",
+            "--oauth-token",
+            "abc",
+            "logout",
+            "--wf-url",
+            "x",
+        ])?;
+        let creds = WriteFreelyCredentials::try_from(&cli)?;
+        assert!(matches!(creds, WriteFreelyCredentials::OAuthToken("abc")));
+        Ok(())
+    }
+
+    #[test]
+    fn credentials_require_token() -> Result<()> {
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["gemfreely", "logout", "--wf-url", "x"])?;
+        assert!(WriteFreelyCredentials::try_from(&cli).is_err());
+        Ok(())
+    }
+
+    /// A request received by serve_http().
+    pub(crate) struct MockRequest {
+        pub method: String,
+        pub path: String,
+        pub body: String,
+    }
+
+    /// Minimal HTTP server for tests, answering each request with the
+    /// status and JSON returned by the handler. Each connection gets
+    /// one answer. Returns the URL of the server.
+    pub(crate) fn serve_http<H>(handler: H) -> Result<Url>
+    where
+        H: Fn(&MockRequest) -> (u16, String) + Send + 'static,
+    {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = std::net::TcpListener::bind("localhost:0")?;
+        let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let mut parts = request_line.split_whitespace();
+                let request = MockRequest {
+                    method: parts.next().unwrap_or_default().to_owned(),
+                    path: parts.next().unwrap_or_default().to_owned(),
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                };
+
+                let (status, json) = handler(&request);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    json.len(),
+                    json
+                );
+            }
+        });
+
+        Ok(url)
+    }
+
+    /// Serve the collection posts of "blog", with the slugs of each
+    /// page given by `page_slugs`.
+    fn serve_collection_pages(page_slugs: fn(usize) -> Vec<String>) -> Result<Url> {
+        serve_http(move |request| {
+            let page = request
+                .path
+                .strip_prefix("/api/collections/blog/posts?page=")
+                .and_then(|page| page.parse().ok())
+                .unwrap_or(0);
+
+            let posts: Vec<_> = page_slugs(page)
+                .into_iter()
+                .map(|slug| {
+                    serde_json::json!({
+                        "id": format!("id-{}", slug), "slug": slug, "title": "",
+                        "body": "", "appearance": "norm", "language": "en",
+                        "rtl": false, "tags": [], "views": 0
+                    })
+                })
+                .collect();
+            let json = serde_json::json!({"code": 200, "data": {"posts": posts}});
+            (200, json.to_string())
+        })
+    }
+
+    #[tokio::test]
+    async fn slugs_from_every_page() -> Result<()> {
+        let url = serve_collection_pages(|page| match page {
+            1 | 2 => (0..50).map(|n| format!("post-{}-{}", page, n)).collect(),
+            _ => vec![],
+        })?;
+
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token")).await?;
+        let slugs: HashSet<_> = wf.slugs().await?.into_iter().collect();
+
+        assert_eq!(slugs.len(), 100);
+        assert!(slugs.contains("post-1-0") && slugs.contains("post-2-49"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn slugs_stop_on_repeated_page() -> Result<()> {
+        // Ignores the page number and always returns the same posts.
+        let url = serve_collection_pages(|_| (0..10).map(|n| format!("post-{}", n)).collect())?;
+
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token")).await?;
+        assert_eq!(wf.slugs().await?.len(), 10);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn slugs_give_up_after_max_pages() -> Result<()> {
+        let url = serve_collection_pages(|page| {
+            (0..10).map(|n| format!("post-{}-{}", page, n)).collect()
+        })?;
+
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token"))
+            .await?
+            .with_max_slug_pages(3);
+        assert!(wf.slugs().await.is_err());
+        Ok(())
+    }
+
+    /// Answer every request with the same status and JSON.
+    fn serve_fixed_response(status: u16, json: &'static str) -> Result<Url> {
+        serve_http(move |_| (status, json.to_owned()))
+    }
+
+    #[tokio::test]
+    async fn server_errors_creating_posts_are_not_retried() -> Result<()> {
+        let posts_sent = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = posts_sent.clone();
+        let url = serve_http(move |request| {
+            if request.method == "POST" {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            (
+                500,
+                r#"{"code":500,"error_msg":"Stored, then failed"}"#.to_owned(),
+            )
+        })?;
+
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token"))
+            .await?
+            .with_max_retries(2);
+        let entry = GemfeedEntry::default().with_body("# Post\n".into());
+
+        assert!(wf.create_post(&entry, false).await.is_err());
+        assert_eq!(posts_sent.load(std::sync::atomic::Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gemfeed_is_headed_by_collection_title() -> Result<()> {
+        let url = serve_http(|request| match request.path.as_str() {
+            "/api/collections/blog" => {
+                let json = serde_json::json!({"code": 200, "data": {
+                    "alias": "blog", "title": "My Blog", "public": true,
+                    "style_sheet": "", "description": "", "verification_link": ""
+                }});
+                (200, json.to_string())
+            }
+            _ => (200, r#"{"code":200,"data":{"posts":[]}}"#.to_owned()),
+        })?;
+
+        let wf = WriteFreely::new(&url, "blog", &AccessToken("token")).await?;
+        let gemfeed = wf.collection_posts_as_gemfeed().await?;
+        assert!(gemfeed.starts_with("# My Blog\n"), "{}", gemfeed);
+        Ok(())
+    }
+
+    const COLLECTION_CREATED: &str = r#"{"code":201,"data":{"alias":"notes","title":"Notes"}}"#;
+    const COLLECTION_EXISTS: &str = r#"{"code":409,"error_msg":"Collection already exists."}"#;
+
+    #[tokio::test]
+    async fn create_collection_succeeds() -> Result<()> {
+        let url = serve_fixed_response(201, COLLECTION_CREATED)?;
+        let wf = WriteFreely::new(&url, "", &AccessToken("token")).await?;
+
+        wf.create_collection("notes", "Notes", Some("Short notes"))
+            .await?;
+        assert!(
+            wf.create_collection_if_missing("notes", "Notes", None)
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn existing_collection_is_ok_if_missing_only() -> Result<()> {
+        let url = serve_fixed_response(409, COLLECTION_EXISTS)?;
+        let wf = WriteFreely::new(&url, "", &AccessToken("token")).await?;
+
+        assert!(
+            !wf.create_collection_if_missing("notes", "Notes", None)
+                .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn existing_collection_is_an_error() -> Result<()> {
+        let url = serve_fixed_response(409, COLLECTION_EXISTS)?;
+        let wf = WriteFreely::new(&url, "", &AccessToken("token")).await?;
+
+        let err = wf
+            .create_collection("notes", "Notes", None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("409"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn exists_from_result_maps_status_codes() {
+        assert!(exists_from_result(Ok(())).unwrap());
+
+        let not_found = Err(WriteFreelyError::ApiError(404, "Not found".into()));
+        assert!(!exists_from_result::<()>(not_found).unwrap());
+
+        let server_error = Err(WriteFreelyError::ApiError(500, "Oops".into()));
+        assert!(exists_from_result::<()>(server_error).is_err());
+    }
 }